@@ -1,7 +1,15 @@
+mod convert;
 mod format;
+mod info;
 mod meta;
+mod number;
 mod parse;
 mod regex;
+mod template;
 
+pub use convert::{can_convert, convert_to_cbz, ConvertError};
 pub use format::{Format, FormatError};
+pub use info::{ComicInfo, InfoError, Prefer, COMIC_INFO};
 pub use meta::{Meta, MetaError};
+pub use number::{Fraction, Number, NumberError};
+pub use template::{Template, TemplateError};
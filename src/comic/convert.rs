@@ -0,0 +1,215 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use thiserror::Error;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use super::Format;
+
+#[derive(Debug, Error)]
+/// Errors that can occur when converting a comic archive.
+pub enum ConvertError {
+    #[error("converting from {0} is not supported")]
+    Unsupported(Format),
+    #[error("failed to open source archive")]
+    Open(#[source] io::Error),
+    #[error("failed to read tar archive")]
+    Tar(#[source] io::Error),
+    #[error("failed to read 7z archive")]
+    SevenZ(#[source] sevenz_rust::Error),
+    #[cfg(feature = "cbr")]
+    #[error("failed to read rar archive")]
+    Rar(#[source] unrar::error::UnrarError),
+    #[error("failed to write zip archive")]
+    Zip(#[source] zip::result::ZipError),
+    #[error("failed to write zip entry")]
+    Write(#[source] io::Error),
+}
+
+/// Whether archives in the provided format can be converted to CBZ by this build.
+///
+/// CBR archives can only be read when built with the `cbr` feature.
+pub fn can_convert(format: Format) -> bool {
+    match format {
+        Format::Cbt | Format::Cb7 => true,
+        Format::Cbr => cfg!(feature = "cbr"),
+        Format::Cbz => false,
+    }
+}
+
+/// Convert the archive at the source path into a CBZ at the destination path.
+///
+/// Entries are written in the order they appear in the source archive,
+/// keeping their names so the page order is unchanged.
+/// Directory entries are skipped.
+pub fn convert_to_cbz(
+    source: &Path,
+    format: Format,
+    destination: &Path,
+) -> Result<(), ConvertError> {
+    let file = File::create(destination).map_err(ConvertError::Write)?;
+    let mut writer = ZipWriter::new(file);
+
+    match format {
+        Format::Cbt => copy_tar(source, &mut writer)?,
+        Format::Cb7 => copy_7z(source, &mut writer)?,
+        #[cfg(feature = "cbr")]
+        Format::Cbr => copy_rar(source, &mut writer)?,
+        _ => return Err(ConvertError::Unsupported(format)),
+    }
+
+    let file = writer.finish().map_err(ConvertError::Zip)?;
+    file.sync_all().map_err(ConvertError::Write)
+}
+
+/// Write a single entry to the zip archive.
+///
+/// Pages are already compressed images, so entries are stored without compression.
+fn write_entry(
+    writer: &mut ZipWriter<File>,
+    name: &str,
+    reader: &mut dyn Read,
+) -> Result<(), ConvertError> {
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Stored)
+        .large_file(true);
+
+    writer
+        .start_file(name, options)
+        .map_err(ConvertError::Zip)?;
+    io::copy(reader, writer).map_err(ConvertError::Write)?;
+
+    Ok(())
+}
+
+/// Copy the entries of a tar archive.
+fn copy_tar(source: &Path, writer: &mut ZipWriter<File>) -> Result<(), ConvertError> {
+    let file = File::open(source).map_err(ConvertError::Open)?;
+    let mut archive = tar::Archive::new(file);
+
+    for entry in archive.entries().map_err(ConvertError::Tar)? {
+        let mut entry = entry.map_err(ConvertError::Tar)?;
+
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let name = entry.path().map_err(ConvertError::Tar)?;
+        let name = name.to_string_lossy().into_owned();
+        write_entry(writer, &name, &mut entry)?;
+    }
+
+    Ok(())
+}
+
+/// Copy the entries of a 7z archive.
+fn copy_7z(source: &Path, writer: &mut ZipWriter<File>) -> Result<(), ConvertError> {
+    let password = sevenz_rust::Password::empty();
+    let mut archive =
+        sevenz_rust::SevenZReader::open(source, password).map_err(ConvertError::SevenZ)?;
+
+    let mut result = Ok(());
+
+    archive
+        .for_each_entries(|entry, reader| {
+            if entry.is_directory() {
+                return Ok(true);
+            }
+
+            result = write_entry(writer, entry.name(), reader);
+            Ok(result.is_ok())
+        })
+        .map_err(ConvertError::SevenZ)?;
+
+    result
+}
+
+/// Copy the entries of a rar archive.
+#[cfg(feature = "cbr")]
+fn copy_rar(source: &Path, writer: &mut ZipWriter<File>) -> Result<(), ConvertError> {
+    let mut archive = unrar::Archive::new(source)
+        .open_for_processing()
+        .map_err(ConvertError::Rar)?;
+
+    while let Some(header) = archive.read_header().map_err(ConvertError::Rar)? {
+        archive = match header.entry().is_file() {
+            true => {
+                let name = header.entry().filename.to_string_lossy().into_owned();
+                let (data, archive) = header.read().map_err(ConvertError::Rar)?;
+                write_entry(writer, &name, &mut data.as_slice())?;
+                archive
+            }
+            false => header.skip().map_err(ConvertError::Rar)?,
+        };
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use temp_dir::TempDir;
+    use zip::ZipArchive;
+
+    use super::*;
+
+    #[test]
+    fn test_convert_tar() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = TempDir::new()?;
+        let source = dir.child("source.cbt");
+        let destination = dir.child("destination.cbz");
+
+        let pages = ["10.jpg", "02.jpg", "01.jpg"];
+
+        let mut builder = tar::Builder::new(File::create(&source)?);
+        for page in pages {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(page.len() as u64);
+            header.set_cksum();
+            builder.append_data(&mut header, page, page.as_bytes())?;
+        }
+        builder.into_inner()?;
+
+        convert_to_cbz(&source, Format::Cbt, &destination)?;
+
+        let mut archive = ZipArchive::new(File::open(&destination)?)?;
+        let names: Vec<&str> = archive.file_names().collect();
+        assert_eq!(names, pages, "pages should keep their order");
+
+        for page in pages {
+            let mut contents = String::new();
+            archive.by_name(page)?.read_to_string(&mut contents)?;
+            assert_eq!(contents, page);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_convert_invalid() {
+        let dir = TempDir::new().expect("should create temp dir");
+        let source = dir.child("source.cb7");
+        let destination = dir.child("destination.cbz");
+
+        std::fs::write(&source, "not an archive").expect("should create source");
+
+        let result = convert_to_cbz(&source, Format::Cb7, &destination);
+        assert!(result.is_err(), "should fail to convert invalid archive");
+
+        let result = convert_to_cbz(&source, Format::Cbz, &destination);
+        assert!(matches!(
+            result,
+            Err(ConvertError::Unsupported(Format::Cbz))
+        ));
+    }
+
+    #[test]
+    fn test_can_convert() {
+        assert!(can_convert(Format::Cbt));
+        assert!(can_convert(Format::Cb7));
+        assert!(!can_convert(Format::Cbz));
+        assert_eq!(can_convert(Format::Cbr), cfg!(feature = "cbr"));
+    }
+}
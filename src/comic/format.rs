@@ -1,6 +1,10 @@
 use std::fmt::Display;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
 use std::str::FromStr;
 
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -8,7 +12,8 @@ use thiserror::Error;
 /// Error that occurs when parsing a comic book format.
 pub struct FormatError;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 /// The format of a comic book file.
 pub enum Format {
     /// 7z archive.
@@ -21,6 +26,30 @@ pub enum Format {
     Cbz,
 }
 
+impl Format {
+    /// Detect the real format of the file at the provided path from its signature.
+    ///
+    /// Returns None if the signature does not match any known format.
+    pub fn detect(path: &Path) -> io::Result<Option<Format>> {
+        let mut header = Vec::with_capacity(262);
+        File::open(path)?.take(262).read_to_end(&mut header)?;
+        Ok(Self::from_signature(&header))
+    }
+
+    /// Get the format matching the signature at the start of a file.
+    pub fn from_signature(header: &[u8]) -> Option<Format> {
+        let zip = [b"PK\x03\x04", b"PK\x05\x06", b"PK\x07\x08"];
+
+        match header {
+            _ if zip.iter().any(|signature| header.starts_with(*signature)) => Some(Self::Cbz),
+            _ if header.starts_with(b"Rar!\x1a\x07") => Some(Self::Cbr),
+            _ if header.starts_with(b"7z\xbc\xaf\x27\x1c") => Some(Self::Cb7),
+            _ if header.get(257..262) == Some(b"ustar") => Some(Self::Cbt),
+            _ => None,
+        }
+    }
+}
+
 impl Display for Format {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -49,3 +78,28 @@ impl FromStr for Format {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_signature() {
+        let mut tar = vec![0; 512];
+        tar[257..262].copy_from_slice(b"ustar");
+
+        let cases: [(&[u8], Option<Format>); 7] = [
+            (b"PK\x03\x04\x14\x00", Some(Format::Cbz)),
+            (b"PK\x05\x06", Some(Format::Cbz)),
+            (b"Rar!\x1a\x07\x01\x00", Some(Format::Cbr)),
+            (b"7z\xbc\xaf\x27\x1c\x00\x04", Some(Format::Cb7)),
+            (&tar, Some(Format::Cbt)),
+            (b"PK", None),
+            (b"", None),
+        ];
+
+        for (header, expected) in cases {
+            assert_eq!(Format::from_signature(header), expected);
+        }
+    }
+}
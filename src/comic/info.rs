@@ -0,0 +1,508 @@
+use std::fmt::Debug;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use clap::ValueEnum;
+use quick_xml::events::{BytesText, Event};
+use quick_xml::{Reader, Writer};
+use thiserror::Error;
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+use super::{Format, Meta, Number};
+use crate::temp;
+
+/// Name of the metadata file inside comic archives.
+pub const COMIC_INFO: &str = "ComicInfo.xml";
+
+/// Document used when an archive does not contain a ComicInfo.xml.
+const EMPTY_XML: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<ComicInfo xmlns:xsd="http://www.w3.org/2001/XMLSchema" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance"></ComicInfo>"#;
+
+/// Elements written from metadata, in schema order.
+const ELEMENTS: [&str; 8] = [
+    "Title",
+    "Series",
+    "Number",
+    "Count",
+    "Volume",
+    "Year",
+    "Publisher",
+    "Tags",
+];
+
+#[derive(Debug, Error)]
+/// Errors that can occur when reading ComicInfo.xml.
+pub enum InfoError {
+    #[error("failed to open archive")]
+    Open(#[source] std::io::Error),
+    #[error("failed to read archive")]
+    Archive(#[source] zip::result::ZipError),
+    #[error("failed to read comic info")]
+    Read(#[source] std::io::Error),
+    #[error("failed to parse comic info")]
+    Parse(#[source] quick_xml::Error),
+    #[error("failed to write comic info")]
+    Write(#[source] std::io::Error),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+/// Which metadata source wins when the file name and archive disagree.
+pub enum Prefer {
+    /// Keep values parsed from the file name.
+    #[default]
+    Filename,
+    /// Keep values read from the archive.
+    Archive,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// Metadata read from a ComicInfo.xml file.
+///
+/// Only the fields that map onto [`Meta`] are kept.
+/// Values that cannot be parsed are treated as missing.
+pub struct ComicInfo {
+    /// Name of the series.
+    pub series: Option<String>,
+    /// Issue number.
+    pub number: Option<Number>,
+    /// Total number of issues in the series.
+    pub count: Option<usize>,
+    /// Volume of the series.
+    pub volume: Option<usize>,
+    /// Issue title.
+    pub title: Option<String>,
+    /// Year of publication.
+    pub year: Option<usize>,
+    /// Publisher of the comic.
+    pub publisher: Option<String>,
+}
+
+impl ComicInfo {
+    /// Read ComicInfo.xml from the zip archive at the provided path.
+    ///
+    /// Returns None if the archive does not contain a ComicInfo.xml.
+    pub fn read(path: &Path) -> Result<Option<ComicInfo>, InfoError> {
+        let file = File::open(path).map_err(InfoError::Open)?;
+        let mut archive = ZipArchive::new(file).map_err(InfoError::Archive)?;
+
+        read_xml(&mut archive)?.map(|xml| xml.parse()).transpose()
+    }
+
+    /// Write the provided metadata to ComicInfo.xml in the zip archive at the provided path.
+    ///
+    /// An existing ComicInfo.xml is updated, keeping any elements not set by the metadata.
+    /// The archive is rewritten to a temporary file which then replaces the original.
+    pub fn write(path: &Path, meta: &Meta) -> Result<(), InfoError> {
+        let file = File::open(path).map_err(InfoError::Open)?;
+        let mut archive = ZipArchive::new(file).map_err(InfoError::Archive)?;
+
+        let xml = read_xml(&mut archive)?;
+        let xml = update_xml(xml.as_deref(), meta)?;
+
+        let temp = temp::path(path);
+
+        if let Err(err) = rewrite(&mut archive, &temp, &xml) {
+            let _ = fs::remove_file(&temp);
+            return Err(err);
+        }
+
+        fs::rename(&temp, path).map_err(InfoError::Write)
+    }
+
+    /// Merge this info into the provided metadata.
+    ///
+    /// Missing values are always filled in, and `prefer`
+    /// decides which value is kept when both are present.
+    pub fn apply(self, meta: &mut Meta, prefer: Prefer) {
+        let mut series = Some(std::mem::take(&mut meta.series));
+        merge("series", &mut series, self.series, prefer);
+        meta.series = series.unwrap_or_default();
+
+        merge("volume", &mut meta.volume, self.volume, prefer);
+        merge("number", &mut meta.number, self.number, prefer);
+        merge("count", &mut meta.of, self.count, prefer);
+        merge("title", &mut meta.title, self.title, prefer);
+        merge("year", &mut meta.year, self.year, prefer);
+        merge("publisher", &mut meta.publisher, self.publisher, prefer);
+    }
+
+    /// Convert this info into metadata for a comic of the provided format.
+    ///
+    /// Returns None if the info does not contain a series.
+    pub fn into_meta(self, format: Format) -> Option<Meta> {
+        Some(Meta {
+            series: self.series?,
+            volume: self.volume,
+            number: self.number,
+            suffix: None,
+            of: self.count,
+            title: self.title,
+            year: self.year,
+            publisher: self.publisher,
+            tags: vec![],
+            format,
+        })
+    }
+
+    /// Set the field matching the provided element name.
+    fn set(&mut self, element: &str, value: &str) {
+        let text = Some(value.to_string()).filter(|v| !v.is_empty());
+
+        match element {
+            "Series" => self.series = text,
+            "Number" => self.number = value.parse().ok(),
+            "Count" => self.count = value.parse().ok(),
+            "Volume" => self.volume = value.parse().ok(),
+            "Title" => self.title = text,
+            "Year" => self.year = value.parse().ok(),
+            "Publisher" => self.publisher = text,
+            _ => {}
+        }
+    }
+}
+
+impl FromStr for ComicInfo {
+    type Err = InfoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut reader = Reader::from_str(s);
+        reader.config_mut().trim_text(true);
+
+        let mut info = ComicInfo::default();
+        let mut elements = vec![];
+
+        loop {
+            match reader.read_event().map_err(InfoError::Parse)? {
+                Event::Start(start) => {
+                    let name = start.local_name();
+                    elements.push(String::from_utf8_lossy(name.as_ref()).into_owned());
+                }
+                Event::End(_) => {
+                    elements.pop();
+                }
+                Event::Text(text) if elements.len() == 2 => {
+                    let text = text.unescape().map_err(InfoError::Parse)?;
+                    info.set(&elements[1], text.trim());
+                }
+                Event::CData(data) if elements.len() == 2 => {
+                    let text = String::from_utf8_lossy(&data);
+                    info.set(&elements[1], text.trim());
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        Ok(info)
+    }
+}
+
+/// Read the contents of ComicInfo.xml from the provided archive.
+fn read_xml(archive: &mut ZipArchive<File>) -> Result<Option<String>, InfoError> {
+    let name = archive
+        .file_names()
+        .find(|name| name.eq_ignore_ascii_case(COMIC_INFO))
+        .map(str::to_string);
+
+    let name = match name {
+        Some(name) => name,
+        None => return Ok(None),
+    };
+
+    let mut xml = String::new();
+    archive
+        .by_name(&name)
+        .map_err(InfoError::Archive)?
+        .read_to_string(&mut xml)
+        .map_err(InfoError::Read)?;
+
+    Ok(Some(xml))
+}
+
+/// Copy the archive to the temporary path, replacing ComicInfo.xml with the provided xml.
+fn rewrite(archive: &mut ZipArchive<File>, temp: &Path, xml: &str) -> Result<(), InfoError> {
+    let file = File::create(temp).map_err(InfoError::Write)?;
+    let mut writer = ZipWriter::new(file);
+
+    for index in 0..archive.len() {
+        let entry = archive.by_index_raw(index).map_err(InfoError::Archive)?;
+
+        if !entry.name().eq_ignore_ascii_case(COMIC_INFO) {
+            writer.raw_copy_file(entry).map_err(InfoError::Archive)?;
+        }
+    }
+
+    writer
+        .start_file(COMIC_INFO, SimpleFileOptions::default())
+        .map_err(InfoError::Archive)?;
+    writer.write_all(xml.as_bytes()).map_err(InfoError::Write)?;
+
+    let file = writer.finish().map_err(InfoError::Archive)?;
+    file.sync_all().map_err(InfoError::Write)
+}
+
+/// Get the value to write to the provided element.
+fn element_value(meta: &Meta, element: &str) -> Option<String> {
+    match element {
+        "Title" => meta.title.clone(),
+        "Series" => Some(meta.series.clone()),
+        "Number" => meta.number.as_ref().map(Number::to_string),
+        "Count" => meta.of.map(|of| of.to_string()),
+        "Volume" => meta.volume.map(|volume| volume.to_string()),
+        "Year" => meta.year.map(|year| year.to_string()),
+        "Publisher" => meta.publisher.clone(),
+        "Tags" => Some(meta.tags.join(", ")).filter(|tags| !tags.is_empty()),
+        _ => None,
+    }
+}
+
+/// Update the provided ComicInfo.xml, or an empty one, with the provided metadata.
+///
+/// Elements set by the metadata are replaced and all other elements are kept.
+fn update_xml(xml: Option<&str>, meta: &Meta) -> Result<String, InfoError> {
+    let mut reader = Reader::from_str(xml.unwrap_or(EMPTY_XML));
+    reader.config_mut().trim_text(true);
+
+    let mut writer = Writer::new_with_indent(vec![], b' ', 2);
+    let mut written = vec![];
+    let mut depth = 0;
+
+    loop {
+        let event = reader.read_event().map_err(InfoError::Parse)?;
+
+        let (name, value) = match &event {
+            Event::Start(start) | Event::Empty(start) if depth == 1 => {
+                let name = String::from_utf8_lossy(start.local_name().as_ref()).into_owned();
+                let value = element_value(meta, &name);
+                (name, value)
+            }
+            _ => (String::new(), None),
+        };
+
+        match (event, value) {
+            (Event::Start(start), Some(value)) => {
+                reader.read_to_end(start.name()).map_err(InfoError::Parse)?;
+                write_element(&mut writer, &name, &value)?;
+                written.push(name);
+            }
+            (Event::Empty(_), Some(value)) => {
+                write_element(&mut writer, &name, &value)?;
+                written.push(name);
+            }
+            (Event::Start(start), None) => {
+                depth += 1;
+                writer
+                    .write_event(Event::Start(start))
+                    .map_err(InfoError::Write)?;
+            }
+            (Event::End(end), _) => {
+                depth -= 1;
+                if depth == 0 {
+                    for element in ELEMENTS
+                        .iter()
+                        .filter(|e| !written.contains(&e.to_string()))
+                    {
+                        if let Some(value) = element_value(meta, element) {
+                            write_element(&mut writer, element, &value)?;
+                        }
+                    }
+                }
+                writer
+                    .write_event(Event::End(end))
+                    .map_err(InfoError::Write)?;
+            }
+            (Event::Eof, _) => break,
+            (event, _) => writer.write_event(event).map_err(InfoError::Write)?,
+        }
+    }
+
+    Ok(String::from_utf8_lossy(&writer.into_inner()).into_owned())
+}
+
+/// Write a simple text element.
+fn write_element(writer: &mut Writer<Vec<u8>>, name: &str, value: &str) -> Result<(), InfoError> {
+    writer
+        .create_element(name)
+        .write_text_content(BytesText::new(value))
+        .map_err(InfoError::Write)?;
+
+    Ok(())
+}
+
+/// Merge a value from the archive into a value from the file name.
+fn merge<T: Debug + PartialEq>(
+    field: &str,
+    filename: &mut Option<T>,
+    archive: Option<T>,
+    prefer: Prefer,
+) {
+    let archive = match archive {
+        Some(archive) => archive,
+        None => return,
+    };
+
+    match filename {
+        Some(value) if *value == archive => {}
+        Some(value) => {
+            log::info!("{field} differs: filename {value:?}, archive {archive:?}");
+            if prefer == Prefer::Archive {
+                *value = archive;
+            }
+        }
+        None => *filename = Some(archive),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use temp_dir::TempDir;
+
+    use super::*;
+
+    /// Write a zip archive with the provided entries.
+    fn write_zip(path: &Path, entries: &[(&str, &str)]) {
+        let file = File::create(path).expect("should create zip file");
+        let mut zip = ZipWriter::new(file);
+
+        for (name, contents) in entries {
+            zip.start_file(*name, SimpleFileOptions::default())
+                .expect("should start zip entry");
+            zip.write_all(contents.as_bytes())
+                .expect("should write zip entry");
+        }
+
+        zip.finish().expect("should finish zip file");
+    }
+
+    const XML: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+        <ComicInfo xmlns:xsd="http://www.w3.org/2001/XMLSchema">
+            <Title>Rebirth &amp; Renewal</Title>
+            <Series>Batman</Series>
+            <Number>1</Number>
+            <Count>-1</Count>
+            <Volume>3</Volume>
+            <Year>2016</Year>
+            <Publisher><![CDATA[DC Comics]]></Publisher>
+            <Pages>
+                <Page Image="0" Type="FrontCover" />
+            </Pages>
+        </ComicInfo>"#;
+
+    #[test]
+    fn test_from_string() -> Result<(), InfoError> {
+        let info: ComicInfo = XML.parse()?;
+
+        let expected = ComicInfo {
+            series: Some("Batman".into()),
+            number: Some(1.into()),
+            count: None,
+            volume: Some(3),
+            title: Some("Rebirth & Renewal".into()),
+            year: Some(2016),
+            publisher: Some("DC Comics".into()),
+        };
+
+        assert_eq!(info, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply() -> Result<(), Box<dyn std::error::Error>> {
+        let info: ComicInfo = XML.parse()?;
+
+        let mut meta: Meta = "Batman v2 002 (2011).cbz".parse()?;
+        info.clone().apply(&mut meta, Prefer::Filename);
+
+        assert_eq!(meta.volume, Some(2));
+        assert_eq!(meta.number, Some(2.into()));
+        assert_eq!(meta.year, Some(2011));
+        assert_eq!(meta.title, Some("Rebirth & Renewal".into()));
+        assert_eq!(meta.publisher, Some("DC Comics".into()));
+
+        let mut meta: Meta = "Batman v2 002 (2011).cbz".parse()?;
+        info.apply(&mut meta, Prefer::Archive);
+
+        assert_eq!(meta.volume, Some(3));
+        assert_eq!(meta.number, Some(1.into()));
+        assert_eq!(meta.year, Some(2016));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = TempDir::new().expect("should create temp dir");
+
+        let with_info = dir.child("with.cbz");
+        let xml = XML.replace("<Year>2016</Year>", "<Writer>Tom King</Writer>");
+        write_zip(&with_info, &[("001.jpg", "page"), ("ComicInfo.xml", &xml)]);
+
+        let without_info = dir.child("without.cbz");
+        write_zip(&without_info, &[("001.jpg", "page")]);
+
+        let meta: Meta = "Batman v3 002 of 12 Title (2017) (Digital) (Scan).cbz".parse()?;
+
+        for path in [&with_info, &without_info] {
+            ComicInfo::write(path, &meta)?;
+
+            let info = ComicInfo::read(path)?.expect("should find comic info");
+            let expected = ComicInfo {
+                series: Some("Batman".into()),
+                number: Some(2.into()),
+                count: Some(12),
+                volume: Some(3),
+                title: Some("Title".into()),
+                year: Some(2017),
+                publisher: info.publisher.clone(),
+            };
+            assert_eq!(info, expected);
+
+            let mut archive = ZipArchive::new(File::open(path)?)?;
+            let mut page = String::new();
+            archive.by_name("001.jpg")?.read_to_string(&mut page)?;
+            assert_eq!(page, "page", "pages should be kept");
+
+            let xml = read_xml(&mut archive)?.expect("should find comic info");
+            assert!(xml.contains("<Tags>Digital, Scan</Tags>"), "{xml}");
+        }
+
+        let xml = read_xml(&mut ZipArchive::new(File::open(&with_info)?)?)?.unwrap_or_default();
+        assert!(xml.contains("<![CDATA[DC Comics]]>"), "{xml}");
+        assert!(xml.contains("<Writer>Tom King</Writer>"), "{xml}");
+        assert!(
+            xml.contains(r#"<Page Image="0" Type="FrontCover" />"#),
+            "{xml}"
+        );
+        assert_eq!(xml.matches("<Series>").count(), 1, "{xml}");
+
+        assert_eq!(
+            fs::read_dir(dir.path())?.count(),
+            2,
+            "temp files should be removed"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read() -> Result<(), InfoError> {
+        let dir = TempDir::new().expect("should create temp dir");
+
+        let with_info = dir.child("with.cbz");
+        write_zip(&with_info, &[("001.jpg", ""), ("comicinfo.xml", XML)]);
+
+        let without_info = dir.child("without.cbz");
+        write_zip(&without_info, &[("001.jpg", "")]);
+
+        let info = ComicInfo::read(&with_info)?.expect("should find comic info");
+        assert_eq!(info.series, Some("Batman".into()));
+
+        assert_eq!(ComicInfo::read(&without_info)?, None);
+
+        Ok(())
+    }
+}
@@ -2,9 +2,10 @@ use std::fmt::Display;
 use std::num::ParseIntError;
 use std::str::FromStr;
 
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use super::{parse, regex, Format, FormatError};
+use super::{parse, regex, Format, FormatError, Number, NumberError};
 
 #[derive(Debug, Error)]
 /// Errors that can occur when parsing metadata.
@@ -13,8 +14,10 @@ pub enum MetaError {
     GetCaptures,
     #[error("failed to parse series name")]
     ParseSeries,
+    #[error("failed to parse volume")]
+    ParseVolume(#[source] ParseIntError),
     #[error("failed to parse issue number")]
-    ParseNumber(#[source] ParseIntError),
+    ParseNumber(#[source] NumberError),
     #[error("failed to parse issue of number")]
     ParseOf(#[source] ParseIntError),
     #[error("failed to parse year")]
@@ -25,7 +28,7 @@ pub enum MetaError {
     ParseFormat(#[source] FormatError),
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 /// Metadata for a comic book file.
 pub struct Meta {
     /// Name of the series.
@@ -34,13 +37,19 @@ pub struct Meta {
     /// even one-shots and volumes are part of a series.
     pub series: String,
 
+    /// Volume of the series.
+    ///
+    /// This is present when a series has been relaunched,
+    /// and is used to keep each run of a series apart.
+    pub volume: Option<usize>,
+
     /// Issue or volume number.
     ///
     /// This should be present for regular issues and volumes,
     /// but often not for one-shots or collected editions.
     ///
     /// Optional since not all comics have a number.
-    pub number: Option<usize>,
+    pub number: Option<Number>,
 
     /// Issue or volume suffix.
     ///
@@ -66,6 +75,12 @@ pub struct Meta {
     /// since year may not be known for all comics.
     pub year: Option<usize>,
 
+    /// Publisher of the comic.
+    ///
+    /// This is never present in file names,
+    /// but may be read from the archive metadata.
+    pub publisher: Option<String>,
+
     /// Tags for the comic.
     ///
     /// This may or may not be present for all comics,
@@ -80,7 +95,11 @@ impl Display for Meta {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.series)?;
 
-        if let Some(number) = self.number {
+        if let Some(volume) = self.volume {
+            write!(f, " v{}", volume)?;
+        }
+
+        if let Some(number) = &self.number {
             write!(f, " {:03}", number)?;
         }
 
@@ -115,6 +134,7 @@ impl FromStr for Meta {
         let caps = regex::META.captures(s).ok_or(MetaError::GetCaptures)?;
 
         let series = parse::series(&caps)?;
+        let volume = parse::volume(&caps)?;
         let number = parse::number(&caps)?;
         let suffix = parse::suffix(&caps);
         let of = parse::of(&caps)?;
@@ -125,11 +145,13 @@ impl FromStr for Meta {
 
         Ok(Self {
             series,
+            volume,
             number,
             suffix,
             of,
             title,
             year,
+            publisher: None,
             tags,
             format,
         })
@@ -139,6 +161,7 @@ impl FromStr for Meta {
 #[cfg(test)]
 pub(crate) mod tests {
     use super::*;
+    use crate::comic::Fraction;
 
     #[test]
     fn test_from_string() -> Result<(), MetaError> {
@@ -147,11 +170,13 @@ pub(crate) mod tests {
                 "Simple 001.cbr",
                 Meta {
                     series: "Simple".into(),
-                    number: Some(1),
+                    volume: None,
+                    number: Some(1.into()),
                     suffix: None,
                     of: None,
                     title: None,
                     year: None,
+                    publisher: None,
                     tags: vec![],
                     format: Format::Cbr,
                 },
@@ -160,11 +185,13 @@ pub(crate) mod tests {
                 "Year 090 (2024).cbt",
                 Meta {
                     series: "Year".into(),
-                    number: Some(90),
+                    volume: None,
+                    number: Some(90.into()),
                     suffix: None,
                     of: None,
                     title: None,
                     year: Some(2024),
+                    publisher: None,
                     tags: vec![],
                     format: Format::Cbt,
                 },
@@ -173,11 +200,13 @@ pub(crate) mod tests {
                 "Dashed - Series-Name 10.cbz",
                 Meta {
                     series: "Dashed - Series-Name".into(),
-                    number: Some(10),
+                    volume: None,
+                    number: Some(10.into()),
                     suffix: None,
                     of: None,
                     title: None,
                     year: None,
+                    publisher: None,
                     tags: vec![],
                     format: Format::Cbz,
                 },
@@ -186,11 +215,13 @@ pub(crate) mod tests {
                 "Tagged (Simple) (Over-Complicated Tag).cbr",
                 Meta {
                     series: "Tagged".into(),
+                    volume: None,
                     number: None,
                     suffix: None,
                     of: None,
                     title: None,
                     year: None,
+                    publisher: None,
                     tags: vec!["Simple".into(), "Over-Complicated Tag".into()],
                     format: Format::Cbr,
                 },
@@ -199,11 +230,13 @@ pub(crate) mod tests {
                 "Year Tagged (2024) (Simple) (Over-Complicated Tag).cbt",
                 Meta {
                     series: "Year Tagged".into(),
+                    volume: None,
                     number: None,
                     suffix: None,
                     of: None,
                     title: None,
                     year: Some(2024),
+                    publisher: None,
                     tags: vec!["Simple".into(), "Over-Complicated Tag".into()],
                     format: Format::Cbt,
                 },
@@ -212,11 +245,13 @@ pub(crate) mod tests {
                 "Special-characters - + & (x) (10) (+) '99 020.cbz",
                 Meta {
                     series: "Special-characters - + & (x) (10) (+) '99".into(),
-                    number: Some(20),
+                    volume: None,
+                    number: Some(20.into()),
                     suffix: None,
                     of: None,
                     title: None,
                     year: None,
+                    publisher: None,
                     tags: vec![],
                     format: Format::Cbz,
                 },
@@ -225,11 +260,13 @@ pub(crate) mod tests {
                 "Limited #01 of #02 (2020) (Tag).cbr",
                 Meta {
                     series: "Limited".into(),
-                    number: Some(1),
+                    volume: None,
+                    number: Some(1.into()),
                     suffix: None,
                     of: Some(2),
                     title: None,
                     year: Some(2020),
+                    publisher: None,
                     tags: vec!["Tag".into()],
                     format: Format::Cbr,
                 },
@@ -238,11 +275,13 @@ pub(crate) mod tests {
                 "Limited #02 (OF #03).cbt",
                 Meta {
                     series: "Limited".into(),
-                    number: Some(2),
+                    volume: None,
+                    number: Some(2.into()),
                     suffix: None,
                     of: Some(3),
                     title: None,
                     year: None,
+                    publisher: None,
                     tags: vec![],
                     format: Format::Cbt,
                 },
@@ -251,11 +290,13 @@ pub(crate) mod tests {
                 "Limited 003 OF #4.cbz",
                 Meta {
                     series: "Limited".into(),
-                    number: Some(3),
+                    volume: None,
+                    number: Some(3.into()),
                     suffix: None,
                     of: Some(4),
                     title: None,
                     year: None,
+                    publisher: None,
                     tags: vec![],
                     format: Format::Cbz,
                 },
@@ -264,11 +305,13 @@ pub(crate) mod tests {
                 "Limited 004 (of 5).cbr",
                 Meta {
                     series: "Limited".into(),
-                    number: Some(4),
+                    volume: None,
+                    number: Some(4.into()),
                     suffix: None,
                     of: Some(5),
                     title: None,
                     year: None,
+                    publisher: None,
                     tags: vec![],
                     format: Format::Cbr,
                 },
@@ -277,11 +320,13 @@ pub(crate) mod tests {
                 "Series With 001 Issue Name (2023).cbt",
                 Meta {
                     series: "Series With".into(),
-                    number: Some(1),
+                    volume: None,
+                    number: Some(1.into()),
                     suffix: None,
                     of: None,
                     title: Some("Issue Name".into()),
                     year: Some(2023),
+                    publisher: None,
                     tags: vec![],
                     format: Format::Cbt,
                 },
@@ -290,11 +335,13 @@ pub(crate) mod tests {
                 "With.Dots 001.cbr",
                 Meta {
                     series: "With.Dots".into(),
-                    number: Some(1),
+                    volume: None,
+                    number: Some(1.into()),
                     suffix: None,
                     of: None,
                     title: None,
                     year: None,
+                    publisher: None,
                     tags: vec![],
                     format: Format::Cbr,
                 },
@@ -303,15 +350,168 @@ pub(crate) mod tests {
                 "With Suffix 001X (2023).cbt",
                 Meta {
                     series: "With Suffix".into(),
-                    number: Some(1),
+                    volume: None,
+                    number: Some(1.into()),
                     suffix: Some("X".into()),
                     of: None,
                     title: None,
                     year: Some(2023),
+                    publisher: None,
                     tags: vec![],
                     format: Format::Cbt,
                 },
             ),
+            (
+                "Batman 000.5 (2016).cbz",
+                Meta {
+                    series: "Batman".into(),
+                    volume: None,
+                    number: Some(Number {
+                        negative: false,
+                        whole: Some(0),
+                        fraction: Some(Fraction::Decimal("5".into())),
+                    }),
+                    suffix: None,
+                    of: None,
+                    title: None,
+                    year: Some(2016),
+                    publisher: None,
+                    tags: vec![],
+                    format: Format::Cbz,
+                },
+            ),
+            (
+                "Saga 1.MU.cbz",
+                Meta {
+                    series: "Saga".into(),
+                    volume: None,
+                    number: Some(Number {
+                        negative: false,
+                        whole: Some(1),
+                        fraction: Some(Fraction::Decimal("MU".into())),
+                    }),
+                    suffix: None,
+                    of: None,
+                    title: None,
+                    year: None,
+                    publisher: None,
+                    tags: vec![],
+                    format: Format::Cbz,
+                },
+            ),
+            (
+                "Hulk ½.cbz",
+                Meta {
+                    series: "Hulk".into(),
+                    volume: None,
+                    number: Some(Number {
+                        negative: false,
+                        whole: None,
+                        fraction: Some(Fraction::Vulgar('½')),
+                    }),
+                    suffix: None,
+                    of: None,
+                    title: None,
+                    year: None,
+                    publisher: None,
+                    tags: vec![],
+                    format: Format::Cbz,
+                },
+            ),
+            (
+                "Detective Comics #-1 (1994).cbr",
+                Meta {
+                    series: "Detective Comics".into(),
+                    volume: None,
+                    number: Some(Number {
+                        negative: true,
+                        whole: Some(1),
+                        fraction: None,
+                    }),
+                    suffix: None,
+                    of: None,
+                    title: None,
+                    year: Some(1994),
+                    publisher: None,
+                    tags: vec![],
+                    format: Format::Cbr,
+                },
+            ),
+            (
+                "Detective Comics #0.cbr",
+                Meta {
+                    series: "Detective Comics".into(),
+                    volume: None,
+                    number: Some(0.into()),
+                    suffix: None,
+                    of: None,
+                    title: None,
+                    year: None,
+                    publisher: None,
+                    tags: vec![],
+                    format: Format::Cbr,
+                },
+            ),
+            (
+                "Batman v3 012 (2017).cbz",
+                Meta {
+                    series: "Batman".into(),
+                    volume: Some(3),
+                    number: Some(12.into()),
+                    suffix: None,
+                    of: None,
+                    title: None,
+                    year: Some(2017),
+                    publisher: None,
+                    tags: vec![],
+                    format: Format::Cbz,
+                },
+            ),
+            (
+                "Batman Vol. 3 013.cbz",
+                Meta {
+                    series: "Batman".into(),
+                    volume: Some(3),
+                    number: Some(13.into()),
+                    suffix: None,
+                    of: None,
+                    title: None,
+                    year: None,
+                    publisher: None,
+                    tags: vec![],
+                    format: Format::Cbz,
+                },
+            ),
+            (
+                "Batman Volume 03 014.cbz",
+                Meta {
+                    series: "Batman".into(),
+                    volume: Some(3),
+                    number: Some(14.into()),
+                    suffix: None,
+                    of: None,
+                    title: None,
+                    year: None,
+                    publisher: None,
+                    tags: vec![],
+                    format: Format::Cbz,
+                },
+            ),
+            (
+                "Batman V2 #001.cbr",
+                Meta {
+                    series: "Batman".into(),
+                    volume: Some(2),
+                    number: Some(1.into()),
+                    suffix: None,
+                    of: None,
+                    title: None,
+                    year: None,
+                    publisher: None,
+                    tags: vec![],
+                    format: Format::Cbr,
+                },
+            ),
         ];
 
         for (input, expected) in cases {
@@ -321,4 +521,26 @@ pub(crate) mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_to_string() -> Result<(), MetaError> {
+        let cases = [
+            ("Simple 1.cbr", "Simple 001.cbr"),
+            ("Batman 000.5 (2016).cbz", "Batman 000.5 (2016).cbz"),
+            ("Saga 1.MU.cbz", "Saga 001.MU.cbz"),
+            ("Hulk ½.cbz", "Hulk ½.cbz"),
+            ("Detective Comics #-1.cbr", "Detective Comics -001.cbr"),
+            ("Detective Comics #0.cbr", "Detective Comics 000.cbr"),
+            ("Batman Vol. 3 012 (2017).cbz", "Batman v3 012 (2017).cbz"),
+            ("Batman Volume 03 012.cbz", "Batman v3 012.cbz"),
+            ("Saga 001 (Tag) (copy 2).cbz", "Saga 001 (Tag).cbz"),
+        ];
+
+        for (input, expected) in cases {
+            let meta: Meta = input.parse()?;
+            assert_eq!(meta.to_string(), expected);
+        }
+
+        Ok(())
+    }
 }
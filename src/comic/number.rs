@@ -0,0 +1,305 @@
+use std::cmp::Ordering;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+#[error("invalid issue number")]
+/// Error that occurs when parsing an issue number.
+pub struct NumberError;
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+/// The fractional part of an issue number.
+pub enum Fraction {
+    /// Digits or letters after a decimal point.
+    ///
+    /// For example the `5` in `000.5` or the `MU` in `1.MU`.
+    Decimal(String),
+
+    /// A vulgar fraction character, such as `½`.
+    Vulgar(char),
+}
+
+impl Fraction {
+    /// Numeric value of the fraction, used for ordering.
+    ///
+    /// Non-numeric decimals such as `MU` have no value and count as zero.
+    fn value(&self) -> f64 {
+        match self {
+            Self::Decimal(digits) => format!("0.{digits}").parse().unwrap_or_default(),
+            Self::Vulgar('¼') => 0.25,
+            Self::Vulgar('½') => 0.5,
+            Self::Vulgar('¾') => 0.75,
+            Self::Vulgar(_) => 0.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Issue or volume number of a comic book.
+///
+/// Numbers keep their original decimal or fractional form
+/// so they can be printed back the way they were parsed.
+pub struct Number {
+    /// Whether the number is negative.
+    ///
+    /// This is used for flashback and other special issues, such as `-1`.
+    pub negative: bool,
+
+    /// Whole part of the number.
+    ///
+    /// Optional since some issues, such as `½`, have no whole part.
+    pub whole: Option<usize>,
+
+    /// Fractional part of the number.
+    ///
+    /// This is not present for regular issues,
+    /// but is used for point one, half and variant issues.
+    pub fraction: Option<Fraction>,
+}
+
+impl Number {
+    /// Numeric value of the number, used for ordering.
+    fn value(&self) -> f64 {
+        let whole = self.whole.unwrap_or_default() as f64;
+        let fraction = self.fraction.as_ref().map(Fraction::value);
+        let value = whole + fraction.unwrap_or_default();
+
+        match self.negative {
+            true => -value,
+            false => value,
+        }
+    }
+}
+
+impl From<usize> for Number {
+    fn from(whole: usize) -> Self {
+        Self {
+            negative: false,
+            whole: Some(whole),
+            fraction: None,
+        }
+    }
+}
+
+impl Ord for Number {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.value()
+            .total_cmp(&other.value())
+            .then_with(|| self.fraction.cmp(&other.fraction))
+            .then_with(|| self.whole.cmp(&other.whole))
+    }
+}
+
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for Number {
+    /// Formats the number, zero padding the whole part to the given width.
+    ///
+    /// The sign is not counted towards the width, so `-1` pads to `-001`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.negative {
+            write!(f, "-")?;
+        }
+
+        if let Some(whole) = self.whole {
+            match (f.sign_aware_zero_pad(), f.width()) {
+                (true, Some(width)) => write!(f, "{:0width$}", whole)?,
+                _ => write!(f, "{}", whole)?,
+            }
+        }
+
+        match &self.fraction {
+            Some(Fraction::Decimal(decimal)) => write!(f, ".{}", decimal),
+            Some(Fraction::Vulgar(vulgar)) => write!(f, "{}", vulgar),
+            None => Ok(()),
+        }
+    }
+}
+
+impl FromStr for Number {
+    type Err = NumberError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, s) = match s.strip_prefix('-') {
+            Some(s) => (true, s),
+            None => (false, s),
+        };
+
+        let (whole, fraction) = match s.char_indices().last() {
+            Some((i, c @ ('¼' | '½' | '¾'))) => (&s[..i], Some(Fraction::Vulgar(c))),
+            _ => match s.split_once('.') {
+                Some((whole, decimal)) => (whole, Some(decimal_fraction(decimal)?)),
+                None => (s, None),
+            },
+        };
+
+        let whole = match whole {
+            "" if fraction.is_some() => None,
+            _ if !whole.bytes().all(|b| b.is_ascii_digit()) => return Err(NumberError),
+            _ => Some(whole.parse::<usize>().map_err(|_| NumberError)?),
+        };
+
+        Ok(Self {
+            negative,
+            whole,
+            fraction,
+        })
+    }
+}
+
+impl Serialize for Number {
+    /// Serializes the number as it is displayed, such as `1.5` or `½`.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Number {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let number = String::deserialize(deserializer)?;
+        number
+            .parse()
+            .map_err(|_| serde::de::Error::custom(format!("invalid number: {number}")))
+    }
+}
+
+/// Parse the part of a number after the decimal point.
+fn decimal_fraction(decimal: &str) -> Result<Fraction, NumberError> {
+    let digits = decimal.bytes().all(|b| b.is_ascii_digit());
+    let letters = decimal.bytes().all(|b| b.is_ascii_alphabetic());
+
+    match !decimal.is_empty() && (digits || letters) {
+        true => Ok(Fraction::Decimal(decimal.to_string())),
+        false => Err(NumberError),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_string() -> Result<(), NumberError> {
+        let cases = [
+            ("1", 1.into()),
+            (
+                "000.5",
+                Number {
+                    negative: false,
+                    whole: Some(0),
+                    fraction: Some(Fraction::Decimal("5".into())),
+                },
+            ),
+            (
+                "1.MU",
+                Number {
+                    negative: false,
+                    whole: Some(1),
+                    fraction: Some(Fraction::Decimal("MU".into())),
+                },
+            ),
+            (
+                "½",
+                Number {
+                    negative: false,
+                    whole: None,
+                    fraction: Some(Fraction::Vulgar('½')),
+                },
+            ),
+            (
+                "-1",
+                Number {
+                    negative: true,
+                    whole: Some(1),
+                    fraction: None,
+                },
+            ),
+            (
+                "2¾",
+                Number {
+                    negative: false,
+                    whole: Some(2),
+                    fraction: Some(Fraction::Vulgar('¾')),
+                },
+            ),
+        ];
+
+        for (input, expected) in cases {
+            let number: Number = input.parse()?;
+            assert_eq!(number, expected);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_string_invalid() {
+        for input in ["", ".", "-", "--1", "1.", "1.5a", "a", "1½½", "1/2"] {
+            assert!(input.parse::<Number>().is_err(), "should reject: {input}");
+        }
+    }
+
+    #[test]
+    fn test_display() -> Result<(), NumberError> {
+        let cases = [
+            ("1", "001"),
+            ("000.5", "000.5"),
+            ("1.MU", "001.MU"),
+            ("½", "½"),
+            ("12½", "012½"),
+            ("1234", "1234"),
+            ("0", "000"),
+            ("-1", "-001"),
+            ("-0.5", "-000.5"),
+        ];
+
+        for (input, expected) in cases {
+            let number: Number = input.parse()?;
+            assert_eq!(format!("{:03}", number), expected);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ordering() -> Result<(), NumberError> {
+        let sorted = [
+            "-2", "-1", "-0.5", "0", "0.5", "½", "1", "1.MU", "1.1", "1½", "1.9", "2", "10",
+        ];
+
+        let mut numbers = sorted
+            .iter()
+            .rev()
+            .map(|n| n.parse())
+            .collect::<Result<Vec<Number>, _>>()?;
+        numbers.sort();
+
+        let numbers: Vec<String> = numbers.iter().map(Number::to_string).collect();
+        assert_eq!(numbers, sorted);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_serde() -> Result<(), serde_json::Error> {
+        for input in ["1", "000.5", "1.MU", "½", "-1", "2¾"] {
+            let number: Number = input.parse().expect("should parse number");
+            let json = serde_json::to_string(&number)?;
+            assert_eq!(serde_json::from_str::<Number>(&json)?, number);
+        }
+
+        assert!(
+            serde_json::from_str::<Number>("\"1/2\"").is_err(),
+            "should reject invalid numbers"
+        );
+
+        Ok(())
+    }
+}
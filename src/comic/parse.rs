@@ -1,4 +1,4 @@
-use super::{Format, MetaError};
+use super::{Format, MetaError, Number};
 
 pub(super) fn series(captures: &regex::Captures) -> Result<String, MetaError> {
     captures
@@ -7,10 +7,17 @@ pub(super) fn series(captures: &regex::Captures) -> Result<String, MetaError> {
         .map(|s| s.as_str().to_string())
 }
 
-pub(super) fn number(captures: &regex::Captures) -> Result<Option<usize>, MetaError> {
+pub(super) fn volume(captures: &regex::Captures) -> Result<Option<usize>, MetaError> {
+    captures
+        .name("volume")
+        .map(|v| v.as_str().parse::<usize>().map_err(MetaError::ParseVolume))
+        .transpose()
+}
+
+pub(super) fn number(captures: &regex::Captures) -> Result<Option<Number>, MetaError> {
     captures
         .name("number")
-        .map(|n| n.as_str().parse::<usize>().map_err(MetaError::ParseNumber))
+        .map(|n| n.as_str().parse::<Number>().map_err(MetaError::ParseNumber))
         .transpose()
 }
 
@@ -42,13 +49,20 @@ pub(super) fn tags(captures: &regex::Captures) -> Vec<String> {
         .map(|t| {
             t.as_str()
                 .split(['(', ')'])
-                .filter(|s| !s.trim().is_empty())
+                .filter(|s| !s.trim().is_empty() && !is_copy(s))
                 .map(|s| s.to_string())
                 .collect()
         })
         .unwrap_or_default()
 }
 
+/// Whether the tag is the numbered suffix added to copies kept when resolving conflicts.
+fn is_copy(tag: &str) -> bool {
+    tag.trim()
+        .strip_prefix("copy ")
+        .is_some_and(|n| n.parse::<u32>().is_ok())
+}
+
 pub(super) fn format(captures: &regex::Captures) -> Result<Format, MetaError> {
     captures
         .name("format")
@@ -6,9 +6,12 @@ static META_REGEX: &str = r"(?x)
         # Series name, or full name for non-serial comics.
         (?<series>[\w\s\#()&'+-.]+?)
 
+        # Series volume, such as v2, Vol. 3 or Volume 03.
+        (?:\s+(?i:v|vol\.?\s*|volume\s*)(?<volume>\d+))?
+
         (?:\s+
-            # Issue or volume number.
-            \#?(?<number>\d+)
+            # Issue or volume number, with optional sign and decimal or fraction.
+            \#?(?<number>-?(?:\d+(?:\.(?:\d+|[[:alpha:]]+)|[¼½¾])?|[¼½¾]))
 
             # Issue of volume suffix.
             (?<suffix>\w+)?
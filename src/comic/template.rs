@@ -0,0 +1,425 @@
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use super::Meta;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+/// Errors that can occur when parsing a template.
+pub enum TemplateError {
+    #[error("unknown field: {0}")]
+    UnknownField(String),
+    #[error("series year is not known, only the cover year, use {{year}} instead")]
+    SeriesYear,
+    #[error("invalid padding for field: {0}")]
+    InvalidPadding(String),
+    #[error("unclosed field")]
+    UnclosedField,
+    #[error("unclosed optional segment")]
+    UnclosedOptional,
+    #[error("unexpected character: {0}")]
+    Unexpected(char),
+    #[error("trailing escape character")]
+    TrailingEscape,
+    #[error("name template must not contain `/`, use a layout for directories")]
+    PathSeparator,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// A metadata field that can be used in a template.
+enum Field {
+    Series,
+    Volume,
+    Number,
+    Suffix,
+    Of,
+    Title,
+    Year,
+    Publisher,
+    Tags,
+    Format,
+}
+
+impl FromStr for Field {
+    type Err = TemplateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "series" => Ok(Self::Series),
+            "volume" => Ok(Self::Volume),
+            "number" => Ok(Self::Number),
+            "suffix" => Ok(Self::Suffix),
+            "of" => Ok(Self::Of),
+            "title" => Ok(Self::Title),
+            "year" => Ok(Self::Year),
+            "publisher" => Ok(Self::Publisher),
+            "tags" => Ok(Self::Tags),
+            "format" => Ok(Self::Format),
+            "series_year" => Err(TemplateError::SeriesYear),
+            _ => Err(TemplateError::UnknownField(s.to_string())),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// Padding applied to a rendered field.
+struct Padding {
+    /// Pad numbers with zeros instead of spaces.
+    zero: bool,
+    /// Minimum width of the rendered field.
+    width: usize,
+}
+
+impl FromStr for Padding {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let zero = s.len() > 1 && s.starts_with('0');
+        let width = s.parse().map_err(|_| ())?;
+        Ok(Self { zero, width })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// A part of a parsed template.
+enum Segment {
+    /// Text copied as is.
+    Literal(String),
+    /// A metadata field with optional padding.
+    Field(Field, Padding),
+    /// Segments only rendered when every field inside is present.
+    Optional(Vec<Segment>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Template for building names from comic metadata.
+///
+/// Fields are written as `{field}` or `{field:03}` to zero pad numbers
+/// to a width, and `{field:3}` to pad with spaces instead.
+/// Text inside `[...]` is only rendered when every field in it is present.
+/// Use `\` to escape any of the special characters `{}[]\`.
+///
+/// The available fields are `series`, `volume`, `number`, `suffix`, `of`,
+/// `title`, `year`, `publisher`, `tags` and `format`. Tags are joined by `) (`,
+/// so `({tags})` renders each tag in its own parentheses.
+/// There is deliberately no `series_year` field, as only the cover year of
+/// each issue is known, and using it would split a series across directories.
+/// Any `/` in a field value is replaced with `-`, and a value of only dots,
+/// such as `..`, has each dot replaced with `_`.
+pub struct Template {
+    /// The template source, kept for display.
+    source: String,
+    /// The parsed template.
+    segments: Vec<Segment>,
+}
+
+impl Template {
+    /// Template used to name files when none is provided.
+    ///
+    /// This matches the name produced by displaying [`Meta`],
+    /// without the format extension.
+    pub const DEFAULT: &'static str =
+        "{series}[ v{volume}][ {number:03}][{suffix}][ of {of}][ {title}][ ({year})][ ({tags})]";
+
+    /// Render the template with the provided metadata.
+    pub fn render(&self, meta: &Meta) -> String {
+        let mut output = String::new();
+        render_segments(&self.segments, meta, &mut output);
+        output
+    }
+
+    /// Parse a template for a path, where each `/` separates a directory.
+    ///
+    /// Unlike parsing a name template, `/` is allowed in the literal text.
+    pub fn parse_path(s: &str) -> Result<Template, TemplateError> {
+        let mut chars = s.chars();
+        let segments = parse_segments(&mut chars, false)?;
+
+        Ok(Self {
+            source: s.to_string(),
+            segments,
+        })
+    }
+
+    /// Render the template as a path, splitting on each `/`.
+    ///
+    /// Empty path components are skipped, and `.` or `..` components have each dot
+    /// replaced with `_`, so the path always stays below the directory it is joined to.
+    pub fn render_path(&self, meta: &Meta) -> PathBuf {
+        self.render(meta)
+            .split('/')
+            .filter(|component| !component.is_empty())
+            .map(|component| match component {
+                "." | ".." => component.replace('.', "_"),
+                _ => component.to_string(),
+            })
+            .collect()
+    }
+}
+
+impl Default for Template {
+    fn default() -> Self {
+        Self::DEFAULT
+            .parse()
+            .expect("default template should be valid")
+    }
+}
+
+impl Display for Template {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl FromStr for Template {
+    type Err = TemplateError;
+
+    /// Parse a name template, which must not contain `/`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let template = Self::parse_path(s)?;
+
+        if has_separator(&template.segments) {
+            return Err(TemplateError::PathSeparator);
+        }
+
+        Ok(template)
+    }
+}
+
+/// Whether the literal text of any segment contains `/`.
+fn has_separator(segments: &[Segment]) -> bool {
+    segments.iter().any(|segment| match segment {
+        Segment::Literal(literal) => literal.contains('/'),
+        Segment::Field(..) => false,
+        Segment::Optional(segments) => has_separator(segments),
+    })
+}
+
+/// Parse segments until the end of input, or the end of an optional segment.
+fn parse_segments(
+    chars: &mut std::str::Chars,
+    optional: bool,
+) -> Result<Vec<Segment>, TemplateError> {
+    let mut segments = vec![];
+    let mut literal = String::new();
+
+    loop {
+        let c = match chars.next() {
+            Some(c) => c,
+            None if optional => return Err(TemplateError::UnclosedOptional),
+            None => break,
+        };
+
+        let segment = match c {
+            '\\' => {
+                literal.push(chars.next().ok_or(TemplateError::TrailingEscape)?);
+                continue;
+            }
+            '{' => parse_field(chars)?,
+            '[' => Segment::Optional(parse_segments(chars, true)?),
+            ']' if optional => break,
+            '}' | ']' => return Err(TemplateError::Unexpected(c)),
+            _ => {
+                literal.push(c);
+                continue;
+            }
+        };
+
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(std::mem::take(&mut literal)));
+        }
+
+        segments.push(segment);
+    }
+
+    if !literal.is_empty() {
+        segments.push(Segment::Literal(literal));
+    }
+
+    Ok(segments)
+}
+
+/// Parse a field after its opening brace.
+fn parse_field(chars: &mut std::str::Chars) -> Result<Segment, TemplateError> {
+    let mut field = String::new();
+
+    loop {
+        match chars.next().ok_or(TemplateError::UnclosedField)? {
+            '}' => break,
+            c => field.push(c),
+        }
+    }
+
+    let (name, padding) = match field.split_once(':') {
+        Some((name, padding)) => {
+            let padding = padding
+                .parse()
+                .map_err(|_| TemplateError::InvalidPadding(name.to_string()))?;
+            (name, padding)
+        }
+        None => (field.as_str(), Padding::default()),
+    };
+
+    Ok(Segment::Field(name.trim().parse()?, padding))
+}
+
+/// Render segments into the output, returning whether all fields were present.
+fn render_segments(segments: &[Segment], meta: &Meta, output: &mut String) -> bool {
+    let mut complete = true;
+
+    for segment in segments {
+        match segment {
+            Segment::Literal(literal) => output.push_str(literal),
+            Segment::Field(field, padding) => match render_field(*field, *padding, meta) {
+                Some(value) => output.push_str(&value),
+                None => complete = false,
+            },
+            Segment::Optional(segments) => {
+                let mut optional = String::new();
+                if render_segments(segments, meta, &mut optional) {
+                    output.push_str(&optional);
+                }
+            }
+        }
+    }
+
+    complete
+}
+
+/// Render a single field, or None if the field is not present.
+fn render_field(field: Field, padding: Padding, meta: &Meta) -> Option<String> {
+    let Padding { zero, width } = padding;
+
+    let value = match field {
+        Field::Series => meta.series.clone(),
+        Field::Volume if zero => format!("{:0width$}", meta.volume?),
+        Field::Volume => meta.volume?.to_string(),
+        Field::Number if zero => format!("{:0width$}", meta.number.as_ref()?),
+        Field::Number => meta.number.as_ref()?.to_string(),
+        Field::Suffix => meta.suffix.clone()?,
+        Field::Of if zero => format!("{:0width$}", meta.of?),
+        Field::Of => meta.of?.to_string(),
+        Field::Title => meta.title.clone()?,
+        Field::Year if zero => format!("{:0width$}", meta.year?),
+        Field::Year => meta.year?.to_string(),
+        Field::Publisher => meta.publisher.clone()?,
+        Field::Tags if meta.tags.is_empty() => return None,
+        Field::Tags => meta.tags.join(") ("),
+        Field::Format => meta.format.to_string(),
+    };
+
+    let value = match !value.is_empty() && value.chars().all(|c| c == '.') {
+        true => value.replace('.', "_"),
+        false => value.replace('/', "-"),
+    };
+
+    Some(format!("{:>width$}", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_matches_display() -> Result<(), Box<dyn std::error::Error>> {
+        let template = Template::default();
+
+        let cases = [
+            "Simple 001.cbr",
+            "Batman v3 012 (2017).cbz",
+            "Limited 004 of 5 Issue Title (2020) (Tag) (Other Tag).cbt",
+            "With Suffix 001X (2023).cbt",
+            "Detective Comics -001.cbr",
+            "Tagged (Simple) (Over-Complicated Tag).cbr",
+        ];
+
+        for name in cases {
+            let meta: Meta = name.parse()?;
+            let rendered = format!("{}.{}", template.render(&meta), meta.format);
+            assert_eq!(rendered, meta.to_string());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_render() -> Result<(), Box<dyn std::error::Error>> {
+        let cases = [
+            (
+                "{series} #{number:03} - {title} ({year})",
+                "Batman 012 Title (2017).cbz",
+                "Batman #012 - Title (2017)",
+            ),
+            (
+                "{series}[ #{number:03}][ - {title}][ ({year})]",
+                "Batman 12.cbz",
+                "Batman #012",
+            ),
+            (
+                "{series}[ v{volume:02}[ #{number}]]",
+                "Batman Vol. 3 12.cbz",
+                "Batman v03 #12",
+            ),
+            (
+                "{series}[ v{volume:02}[ #{number}]]",
+                "Batman 12.cbz",
+                "Batman",
+            ),
+            ("{number:4}|{format}", "Batman 12.cbr", "  12|cbr"),
+            (r"\[{year}\] {series}", "Batman (2017).cbz", "[2017] Batman"),
+            ("{series}", ".. 002.cbz", "__"),
+        ];
+
+        for (template, name, expected) in cases {
+            let template: Template = template.parse()?;
+            let meta: Meta = name.parse()?;
+            assert_eq!(template.render(&meta), expected);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_path() -> Result<(), Box<dyn std::error::Error>> {
+        let cases = [
+            ("{series}[/v{volume}]", "Batman v3 012.cbz", "Batman/v3"),
+            ("{series}[/v{volume}]", "Batman 012.cbz", "Batman"),
+            ("{format}/{series}/", "Batman 012.cbr", "cbr/Batman"),
+            (
+                "{format}/[{title}]/{series}",
+                "Batman 012.cbr",
+                "cbr/Batman",
+            ),
+        ];
+
+        for (template, name, expected) in cases {
+            let template = Template::parse_path(template)?;
+            let meta: Meta = name.parse()?;
+            assert_eq!(template.render_path(&meta), PathBuf::from(expected));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let cases = [
+            ("{writer}", TemplateError::UnknownField("writer".into())),
+            ("{series} ({series_year})", TemplateError::SeriesYear),
+            ("{number:x}", TemplateError::InvalidPadding("number".into())),
+            ("{series", TemplateError::UnclosedField),
+            ("[{series}", TemplateError::UnclosedOptional),
+            ("{series}]", TemplateError::Unexpected(']')),
+            ("series}", TemplateError::Unexpected('}')),
+            ("{series}\\", TemplateError::TrailingEscape),
+            ("{series}/{number:03}", TemplateError::PathSeparator),
+            ("{series}[/{title}]", TemplateError::PathSeparator),
+        ];
+
+        for (template, expected) in cases {
+            assert_eq!(template.parse::<Template>(), Err(expected));
+        }
+    }
+}
@@ -1,3 +1,5 @@
 mod args;
+mod exit;
 
-pub use args::Args;
+pub use args::{ApplyArgs, Args, Command, GapsArgs, PlanArgs, QueryArgs, UndoArgs};
+pub use exit::Exit;
@@ -1,12 +1,26 @@
-use clap::{ArgAction, Parser};
+use clap::{ArgAction, Args as ClapArgs, Parser, Subcommand};
 
 use std::path::PathBuf;
 
+use globset::Glob;
+
+use crate::comic::{Prefer, Template};
+use crate::organise::{parse_group, parse_mode, parse_user, Conflict, Layout, Transfer};
+
 #[derive(Clone, Debug, Default, Parser)]
-#[command(version, author, about)]
-#[group(id = "noisy", multiple = true)]
+#[command(
+    version,
+    author,
+    about,
+    after_long_help = "Exit codes: 0 success, 1 some files failed or could not be parsed, \
+        2 invalid arguments or configuration, 130 interrupted."
+)]
 /// Arguments for the application binary.
 pub struct Args {
+    /// Command to run instead of organising files.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// A list of files or directories to organise.
     ///
     /// For a directory, each direct child file will be organised.
@@ -15,27 +29,157 @@ pub struct Args {
     /// Output directory for organised files.
     ///
     /// If the directory does not exist, it will be created.
+    /// With --in-place, this only keeps the journal, index and overrides.
     ///
-    /// If not provided, the current directory will be used.
-    #[arg(short, long, default_value = ".", value_name = "DIR")]
-    pub output: PathBuf,
+    /// If not provided, the current directory will be used, or with --in-place,
+    /// no journal, index or overrides will be kept.
+    #[arg(short, long, value_name = "DIR")]
+    pub output: Option<PathBuf>,
 
     /// Organise files into subdirectories by series.
     ///
+    /// Comics with a volume are placed in a volume subdirectory of the series.
+    ///
     /// If not provided, files will be placed in the output directory.
     #[arg(short, long)]
     pub series: bool,
 
-    /// Move files instead of copying them.
+    /// Template for the directory layout inside the output directory.
+    ///
+    /// Uses the same fields as --template, with `/` separating directories,
+    /// for example `{format}/{series}[/vol{volume}]`.
+    /// There is no series year field, as only the cover year of each issue is known.
+    ///
+    /// If not provided, files will be placed according to --series.
+    #[arg(long, conflicts_with = "series", value_name = "TEMPLATE")]
+    pub layout: Option<Layout>,
+
+    /// Template used to name organised files.
+    ///
+    /// Fields are written as `{field}`, or `{field:03}` to zero pad numbers.
+    /// Text inside `[...]` is only kept when every field in it is present.
+    ///
+    /// Available fields: series, volume, number, suffix, of, title, year, publisher, tags, format.
+    /// The format extension is always appended to the name.
+    /// Names cannot contain `/`, use --layout to organise files into directories.
+    ///
+    /// If not provided, the default naming template will be used.
+    #[arg(long, default_value = Template::DEFAULT, value_name = "TEMPLATE")]
+    pub template: Template,
+
+    /// Read metadata from ComicInfo.xml in CBZ archives.
+    ///
+    /// Archive metadata fills in values missing from the file name,
+    /// and is used on its own when the file name cannot be parsed.
+    ///
+    /// If not provided, only the file name will be used.
+    #[arg(long)]
+    pub read_info: bool,
+
+    /// Metadata source to keep when the file name and archive disagree.
+    ///
+    /// If not provided, values from the file name will be kept.
+    #[arg(
+        long,
+        value_enum,
+        default_value_t,
+        requires = "read_info",
+        value_name = "SOURCE"
+    )]
+    pub prefer: Prefer,
+
+    /// Write metadata to ComicInfo.xml in organised CBZ archives.
+    ///
+    /// An existing ComicInfo.xml is updated, keeping any other values.
+    /// Files organised with a hardlink or symlink are skipped, as writing would replace the link.
+    ///
+    /// If not provided, organised archives will not be changed.
+    #[arg(long)]
+    pub write_info: bool,
+
+    /// Use the archive format detected from file contents for the extension.
+    ///
+    /// Extensions that do not match the contents are always reported.
+    ///
+    /// If not provided, files will keep their original extension.
+    #[arg(long)]
+    pub fix_extension: bool,
+
+    /// Convert CB7, CBT and CBR archives to CBZ.
+    ///
+    /// CBR archives can only be converted when built with the `cbr` feature,
+    /// and are otherwise transferred unconverted.
+    ///
+    /// When moving, the converted source is kept in the journal so it can be restored,
+    /// unless --no-backup is used.
+    ///
+    /// If not provided, files will keep their original format.
+    #[arg(long)]
+    pub convert: bool,
+
+    /// Rename files where they are, instead of organising them into the output directory.
+    ///
+    /// Each file stays in its own directory, or a series or layout subdirectory of it,
+    /// and is moved with a rename. Files already in the right subdirectory are not nested again.
+    ///
+    /// The journal, index and overrides are only kept when an output directory is given,
+    /// so they are not written to wherever cyborg is run from.
+    ///
+    /// If not provided, files will be organised into the output directory.
+    #[arg(long, conflicts_with = "transfer")]
+    pub in_place: bool,
+
+    /// How files are transferred to the output directory.
+    ///
+    /// Hardlinks and reflinks fall back to copying when they are not supported,
+    /// such as across devices. Converted files are always written as new files.
     ///
     /// If not provided, files will be copied to the output directory.
-    #[arg(short, long)]
-    pub move_files: bool,
+    #[arg(short, long, value_enum, default_value_t, value_name = "MODE")]
+    pub transfer: Transfer,
+
+    /// Keep the modification and access times of source files.
+    ///
+    /// If not provided, copied and converted files will have new times.
+    #[arg(long)]
+    pub preserve_times: bool,
+
+    /// Copy extended attributes from source files.
+    ///
+    /// Attributes that cannot be written to the output are skipped with a warning.
+    ///
+    /// If not provided, copied and converted files will have no extended attributes.
+    #[arg(long)]
+    pub preserve_xattrs: bool,
+
+    /// User name or id to own organised files and created directories.
+    ///
+    /// If not provided, ownership will not be changed.
+    #[arg(long, value_parser = parse_user, value_name = "USER")]
+    pub owner: Option<u32>,
+
+    /// Group name or id to own organised files and created directories.
+    ///
+    /// If not provided, the group will not be changed.
+    #[arg(long, value_parser = parse_group, value_name = "GROUP")]
+    pub group: Option<u32>,
+
+    /// Octal permissions for organised files, like `664`.
+    ///
+    /// If not provided, copied files will keep the permissions of the source.
+    #[arg(long, value_parser = parse_mode, value_name = "MODE")]
+    pub file_mode: Option<u32>,
+
+    /// Octal permissions for created directories, like `2775`.
+    ///
+    /// If not provided, directories will be created with the default permissions.
+    #[arg(long, value_parser = parse_mode, value_name = "MODE")]
+    pub dir_mode: Option<u32>,
 
     /// Print steps without making changes (implies -vv).
     ///
     /// If not provided, changes will be made to the filesystem.
-    #[arg(short, long, group = "noisy")]
+    #[arg(short, long, global = true)]
     pub dry_run: bool,
 
     /// Stop organising after the first error.
@@ -44,10 +188,22 @@ pub struct Args {
     #[arg(short, long, default_value = "false")]
     pub exit: bool,
 
-    /// Overwrite existing files in the output directory.
+    /// What to do when an organised file already exists in the output directory.
     ///
-    /// If not provided, existing files will be skipped.
-    #[arg(short, long)]
+    /// Files kept by keep-both and skip-identical get a numbered suffix like `(copy 2)`,
+    /// and move-to-conflicts puts the older file in the `conflicts` directory of the output.
+    ///
+    /// If not provided, existing files will be kept and new files skipped.
+    #[arg(long, value_enum, default_value_t, value_name = "POLICY")]
+    pub conflict: Conflict,
+
+    /// Overwrite existing files in the output directory (same as --conflict overwrite).
+    ///
+    /// Overwritten files are kept in the journal so they can be restored,
+    /// unless --no-backup is used.
+    ///
+    /// If not provided, the --conflict policy will be used.
+    #[arg(short, long, conflicts_with = "conflict")]
     pub force: bool,
 
     /// Recursively organise files in subdirectories.
@@ -56,15 +212,239 @@ pub struct Args {
     #[arg(short, long)]
     pub recursive: bool,
 
+    /// Maximum depth of subdirectories to organise when recursive.
+    ///
+    /// A depth of 1 organises files in direct subdirectories only.
+    ///
+    /// If not provided, all subdirectories will be organised.
+    #[arg(long, requires = "recursive", value_name = "DEPTH")]
+    pub max_depth: Option<usize>,
+
+    /// Only organise files matching a glob pattern (can be used multiple times).
+    ///
+    /// Patterns match paths relative to each directory being organised,
+    /// for example `*.cb?`. Files provided directly are always organised.
+    ///
+    /// If not provided, all files will be organised.
+    #[arg(long, value_name = "GLOB")]
+    pub include: Vec<Glob>,
+
+    /// Skip files and directories matching a glob pattern (can be used multiple times).
+    ///
+    /// Patterns match paths relative to each directory being organised,
+    /// for example `*.part` or `Thumbs.db`.
+    ///
+    /// If not provided, no files will be skipped.
+    #[arg(long, value_name = "GLOB")]
+    pub exclude: Vec<Glob>,
+
+    /// Write a JSON report of the outcome of each file to the provided path.
+    ///
+    /// The report lists every source and destination with its outcome,
+    /// along with the number of files for each outcome.
+    ///
+    /// If not provided, only a summary will be printed.
+    #[arg(long, value_name = "FILE")]
+    pub report: Option<PathBuf>,
+
+    /// Remove source directories left empty after moving files.
+    ///
+    /// Directories are removed bottom-up, and the provided paths are never removed.
+    ///
+    /// If not provided, source directories will be kept.
+    #[arg(long)]
+    pub prune_empty: bool,
+
+    /// Treat files matching a name pattern as junk when pruning (can be used multiple times).
+    ///
+    /// Junk files, such as `.DS_Store` or `Thumbs.db`, are not organised
+    /// and are removed along with a directory that is otherwise empty.
+    ///
+    /// If not provided, any file will keep a directory.
+    #[arg(long, requires = "prune_empty", value_name = "GLOB")]
+    pub junk: Vec<Glob>,
+
+    /// Do not keep an index of organised files.
+    ///
+    /// The index lets later runs skip files that are unchanged since they were organised,
+    /// and lets the library be queried without walking the output directory.
+    ///
+    /// If not provided, the index is kept in `.cyborg/index.json` in the output directory.
+    #[arg(long)]
+    pub no_index: bool,
+
+    /// Organise every file, even if it is unchanged since the last run.
+    ///
+    /// Use this after changing the template or layout, so organised files are placed again.
+    ///
+    /// If not provided, files in the index that have not changed will be skipped.
+    #[arg(long, conflicts_with = "no_index")]
+    pub rescan: bool,
+
+    /// Confirm each proposed rename, and choose how to organise files that cannot be parsed.
+    ///
+    /// Unparseable files can be organised using a suggestion, or details typed by hand.
+    /// Answers are remembered in the output directory, so later runs do not ask again
+    /// while the file is unchanged.
+    ///
+    /// If not provided, files are organised without asking, and unparseable files are reported.
+    #[arg(short, long)]
+    pub interactive: bool,
+
+    /// Use the answers remembered by --interactive, without asking about other files.
+    ///
+    /// Answers are kept in `.cyborg/overrides.json` in the output directory, by the path of
+    /// each file relative to the directory it was organised from.
+    ///
+    /// If not provided, remembered answers are only used with --interactive.
+    #[arg(long)]
+    pub use_overrides: bool,
+
+    /// Keep watching the paths and organise new files as they arrive.
+    ///
+    /// Existing files are organised first. New files are organised once they
+    /// have stopped growing. Stops cleanly on SIGINT or SIGTERM.
+    ///
+    /// If not provided, the paths will be organised once.
+    #[arg(short, long)]
+    pub watch: bool,
+
+    /// Seconds a watched file must stop changing before it is organised.
+    ///
+    /// If not provided, files will be organised after 5 seconds.
+    #[arg(long, default_value_t = 5, requires = "watch", value_name = "SECONDS")]
+    pub settle: u64,
+
+    /// Do not record changes in a journal.
+    ///
+    /// Journals are written to `.cyborg/journal` in the output directory,
+    /// and are used by the undo command to restore organised files.
+    ///
+    /// If not provided, a journal will be written for each run that makes changes.
+    #[arg(long)]
+    pub no_journal: bool,
+
+    /// Do not keep backups of overwritten, modified or removed files in the journal.
+    ///
+    /// Backups are kept in `.cyborg/journal` until the journal is deleted, so this
+    /// frees the space used by --force, --write-info and moving with --convert.
+    /// Changes to those files cannot be undone.
+    ///
+    /// If not provided, backups will be kept so every change can be undone.
+    #[arg(long, conflicts_with = "no_journal")]
+    pub no_backup: bool,
+
     /// Increase verbosity (can be used multiple times).
     ///
     /// If not provided, only errors will be logged.
-    #[arg(short, long, action = ArgAction::Count, group = "noisy")]
+    #[arg(short, long, action = ArgAction::Count, global = true)]
     pub verbose: u8,
 
     /// Suppress all output (conflicts with verbose).
     ///
     /// If not provided, output will be printed as normal.
-    #[arg(short, long, conflicts_with = "noisy")]
+    #[arg(short, long, global = true, conflicts_with_all = ["verbose", "dry_run"])]
     pub quiet: bool,
 }
+
+#[derive(Clone, Debug, Subcommand)]
+/// Commands for the application binary.
+pub enum Command {
+    /// Undo the changes made by a previous run.
+    ///
+    /// Files that have changed since they were organised are not restored.
+    Undo(UndoArgs),
+
+    /// List organised files from the library index.
+    ///
+    /// The index is read from the output directory, without walking it.
+    Query(QueryArgs),
+
+    /// Report missing issues in each series of an organised library.
+    ///
+    /// Files are grouped by series and volume, and limited series are checked against
+    /// their total number of issues.
+    Gaps(GapsArgs),
+
+    /// Plan how to organise files without making any changes, printing the plan as JSON.
+    ///
+    /// Options for organising are given before the command. The plan can be reviewed
+    /// or edited, then applied with the apply command.
+    Plan(PlanArgs),
+
+    /// Apply a plan made by the plan command.
+    ///
+    /// Options for organising are given before the command. Files are organised into
+    /// the output directory the plan was made for.
+    Apply(ApplyArgs),
+}
+
+#[derive(Clone, Debug, Default, ClapArgs)]
+/// Arguments for the undo command.
+pub struct UndoArgs {
+    /// Journal of the run to undo.
+    ///
+    /// If not provided, the latest journal in the output directory will be used.
+    pub journal: Option<PathBuf>,
+
+    /// Output directory of the run to undo.
+    ///
+    /// If not provided, the current directory will be used.
+    #[arg(short, long, default_value = ".", value_name = "DIR")]
+    pub output: PathBuf,
+}
+
+#[derive(Clone, Debug, Default, ClapArgs)]
+/// Arguments for the query command.
+pub struct QueryArgs {
+    /// Only list files in series containing this text, ignoring case.
+    ///
+    /// If not provided, every file in the index will be listed.
+    #[arg(short, long, value_name = "TEXT")]
+    pub series: Option<String>,
+
+    /// Print the indexed files as JSON, including their metadata.
+    ///
+    /// If not provided, the path of each file will be printed.
+    #[arg(long)]
+    pub json: bool,
+
+    /// Output directory containing the index.
+    ///
+    /// If not provided, the current directory will be used.
+    #[arg(short, long, default_value = ".", value_name = "DIR")]
+    pub output: PathBuf,
+}
+
+#[derive(Clone, Debug, Default, ClapArgs)]
+/// Arguments for the gaps command.
+pub struct GapsArgs {
+    /// Print the gaps as JSON, for feeding a wanted list.
+    ///
+    /// If not provided, each series with gaps will be printed on its own line.
+    #[arg(long)]
+    pub json: bool,
+
+    /// Output directory containing the organised library.
+    ///
+    /// If not provided, the current directory will be used.
+    #[arg(short, long, default_value = ".", value_name = "DIR")]
+    pub output: PathBuf,
+}
+
+#[derive(Clone, Debug, Default, ClapArgs)]
+/// Arguments for the plan command.
+pub struct PlanArgs {
+    /// A list of files or directories to plan organising.
+    ///
+    /// For a directory, each direct child file will be planned.
+    pub paths: Vec<PathBuf>,
+}
+
+#[derive(Clone, Debug, Default, ClapArgs)]
+/// Arguments for the apply command.
+pub struct ApplyArgs {
+    /// Plan to apply, as written by the plan command.
+    #[arg(value_name = "PLAN")]
+    pub plan: PathBuf,
+}
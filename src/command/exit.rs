@@ -0,0 +1,60 @@
+use std::process::ExitCode;
+
+use crate::organise::Report;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Exit codes for the application binary.
+pub enum Exit {
+    /// Every file was organised or skipped.
+    Success = 0,
+    /// Some files failed or could not be parsed.
+    Partial = 1,
+    /// The arguments or configuration were invalid, so nothing was organised.
+    ///
+    /// This matches the exit code used for invalid arguments.
+    Config = 2,
+    /// The run was stopped by a signal before every file was organised.
+    Interrupted = 130,
+}
+
+impl Exit {
+    /// Get the exit code for the outcome of a run.
+    pub fn from_report(report: &Report) -> Exit {
+        match [report.interrupted, report.has_failures()] {
+            [true, _] => Exit::Interrupted,
+            [false, true] => Exit::Partial,
+            [false, false] => Exit::Success,
+        }
+    }
+}
+
+impl From<Exit> for ExitCode {
+    fn from(exit: Exit) -> Self {
+        ExitCode::from(exit as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::organise::{Outcome, Record};
+
+    #[test]
+    fn test_from_report() {
+        let mut report = Report::new(false);
+        assert_eq!(Exit::from_report(&report), Exit::Success);
+
+        report.add(Record {
+            source: PathBuf::from("notes.txt"),
+            destination: None,
+            outcome: Outcome::Unparseable,
+            reason: None,
+        });
+        assert_eq!(Exit::from_report(&report), Exit::Partial);
+
+        report.interrupted = true;
+        assert_eq!(Exit::from_report(&report), Exit::Interrupted);
+    }
+}
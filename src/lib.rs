@@ -2,3 +2,4 @@ pub mod comic;
 pub mod command;
 pub mod log;
 pub mod organise;
+mod temp;
@@ -1,20 +1,228 @@
+use std::io::IsTerminal;
+use std::process::ExitCode;
+
 use clap::Parser;
 use indicatif::MultiProgress;
 
-use cyborg::command::Args;
+use anyhow::Context;
+use cyborg::command::{ApplyArgs, Args, Command, Exit, GapsArgs, PlanArgs, QueryArgs, UndoArgs};
 use cyborg::log;
-use cyborg::organise::{Organiser, Settings};
+use cyborg::organise::{undo, Gaps, Index, Journal, Organiser, Plan, Settings};
 
-fn main() -> anyhow::Result<()> {
+fn main() -> ExitCode {
     let args = Args::parse();
 
     let multibar = MultiProgress::new();
 
-    log::init(&args, multibar.clone())?;
+    if let Err(err) = log::init(&args, multibar.clone()) {
+        eprintln!("Error: {err:?}");
+        return Exit::Config.into();
+    }
+
+    let result = match &args.command {
+        Some(Command::Undo(undo_args)) => undo_run(undo_args, args.dry_run),
+        Some(Command::Query(query_args)) => query_run(query_args),
+        Some(Command::Gaps(gaps_args)) => gaps_run(gaps_args),
+        Some(Command::Plan(plan_args)) => plan_run(&args, plan_args, multibar),
+        Some(Command::Apply(apply_args)) => apply_run(&args, apply_args, multibar),
+        None => organise_run(&args, multibar),
+    };
+
+    match result {
+        Ok(exit) => exit.into(),
+        Err((exit, err)) => {
+            eprintln!("Error: {err:?}");
+            exit.into()
+        }
+    }
+}
+
+/// Organise the provided paths, printing a summary of the run.
+fn organise_run(args: &Args, multibar: MultiProgress) -> Result<Exit, (Exit, anyhow::Error)> {
+    if let Some(output) = args
+        .output
+        .as_ref()
+        .filter(|output| output.exists() && !output.is_dir())
+    {
+        let err = anyhow::anyhow!("output path is not a directory: {}", output.display());
+        return Err((Exit::Config, err));
+    }
+
+    if args.interactive && !std::io::stdin().is_terminal() {
+        let err = anyhow::anyhow!("interactive mode needs a terminal");
+        return Err((Exit::Config, err));
+    }
+
+    let settings = Settings::from_args(args);
+
+    let organiser = Organiser::new(settings, multibar.clone());
+
+    organiser
+        .stop_on_signals()
+        .map_err(|err| (Exit::Config, err))?;
+
+    let report = match args.watch {
+        true => organiser.watch(args.paths.clone()),
+        false => organiser.organise(args.paths.clone()),
+    };
+
+    let report = report.map_err(|err| (Exit::Partial, err))?;
 
-    let settings = Settings::from_args(&args);
+    if !args.quiet {
+        multibar.suspend(|| eprintln!("{report}"));
+    }
+
+    if let Some(path) = &args.report {
+        report
+            .write_json(path)
+            .context("writing report")
+            .map_err(|err| (Exit::Partial, err))?;
+    }
+
+    Ok(Exit::from_report(&report))
+}
+
+/// Plan organising the provided paths, printing the plan as JSON.
+///
+/// Exits as organising would, so unparseable or failed files are not missed.
+fn plan_run(
+    args: &Args,
+    plan_args: &PlanArgs,
+    multibar: MultiProgress,
+) -> Result<Exit, (Exit, anyhow::Error)> {
+    if let Some(output) = args
+        .output
+        .as_ref()
+        .filter(|output| output.exists() && !output.is_dir())
+    {
+        let err = anyhow::anyhow!("output path is not a directory: {}", output.display());
+        return Err((Exit::Config, err));
+    }
+
+    if args.interactive && !std::io::stdin().is_terminal() {
+        let err = anyhow::anyhow!("interactive mode needs a terminal");
+        return Err((Exit::Config, err));
+    }
+
+    let settings = Settings::from_args(args);
 
     let organiser = Organiser::new(settings, multibar);
 
-    organiser.organise(args.paths)
+    let (plan, report) = organiser
+        .plan(plan_args.paths.clone())
+        .map_err(|err| (Exit::Partial, err))?;
+
+    plan.write_json(std::io::stdout().lock())
+        .context("writing plan")
+        .map_err(|err| (Exit::Partial, err))?;
+
+    Ok(Exit::from_report(&report))
+}
+
+/// Apply the provided plan, printing a summary of the run.
+fn apply_run(
+    args: &Args,
+    apply_args: &ApplyArgs,
+    multibar: MultiProgress,
+) -> Result<Exit, (Exit, anyhow::Error)> {
+    let plan = Plan::read(&apply_args.plan)
+        .with_context(|| format!("reading plan: {}", apply_args.plan.display()))
+        .map_err(|err| (Exit::Config, err))?;
+
+    let settings = Settings {
+        output: plan.output.clone(),
+        ..Settings::from_args(args)
+    };
+
+    let organiser = Organiser::new(settings, multibar.clone());
+
+    organiser
+        .stop_on_signals()
+        .map_err(|err| (Exit::Config, err))?;
+
+    let report = organiser.apply(plan).map_err(|err| (Exit::Partial, err))?;
+
+    if !args.quiet {
+        multibar.suspend(|| eprintln!("{report}"));
+    }
+
+    if let Some(path) = &args.report {
+        report
+            .write_json(path)
+            .context("writing report")
+            .map_err(|err| (Exit::Partial, err))?;
+    }
+
+    Ok(Exit::from_report(&report))
+}
+
+/// Undo the run recorded in the provided or latest journal.
+fn undo_run(args: &UndoArgs, dry_run: bool) -> Result<Exit, (Exit, anyhow::Error)> {
+    let journal = match &args.journal {
+        Some(journal) => journal.clone(),
+        None => Journal::latest(&args.output)
+            .context("finding latest journal")
+            .and_then(|journal| journal.context("no journal found in output directory"))
+            .map_err(|err| (Exit::Config, err))?,
+    };
+
+    undo(&journal, dry_run).map_err(|err| (Exit::Partial, err))?;
+
+    Ok(Exit::Success)
+}
+
+/// Print the indexed files matching the query.
+fn query_run(args: &QueryArgs) -> Result<Exit, (Exit, anyhow::Error)> {
+    let index = Index::load(&args.output)
+        .context("loading index")
+        .map_err(|err| (Exit::Config, err))?;
+
+    let series = args.series.as_deref().map(str::to_lowercase);
+
+    let files: Vec<_> = index
+        .files()
+        .filter(|item| match &series {
+            Some(series) => item.meta.series.to_lowercase().contains(series),
+            None => true,
+        })
+        .collect();
+
+    match args.json {
+        true => {
+            let json = serde_json::to_string_pretty(&files)
+                .context("serializing indexed files")
+                .map_err(|err| (Exit::Partial, err))?;
+            println!("{json}");
+        }
+        false => {
+            for item in files {
+                println!("{}", item.destination.display());
+            }
+        }
+    }
+
+    Ok(Exit::Success)
+}
+
+/// Print the missing issues in each series of the library.
+fn gaps_run(args: &GapsArgs) -> Result<Exit, (Exit, anyhow::Error)> {
+    let gaps = Gaps::scan(&args.output)
+        .context("scanning library")
+        .map_err(|err| (Exit::Config, err))?;
+
+    match args.json {
+        true => {
+            let json = serde_json::to_string_pretty(&gaps)
+                .context("serializing gaps")
+                .map_err(|err| (Exit::Partial, err))?;
+            println!("{json}");
+        }
+        false => {
+            for gap in gaps {
+                println!("{gap}");
+            }
+        }
+    }
+
+    Ok(Exit::Success)
 }
@@ -1,5 +1,31 @@
+mod conflict;
+mod filter;
+mod gaps;
+mod index;
+mod journal;
+mod layout;
 mod organiser;
+mod overrides;
+mod plan;
+mod planner;
+mod preserve;
+mod prompt;
+mod report;
 mod settings;
+mod transfer;
+mod watch;
 
+pub use conflict::{Conflict, Resolution};
+pub use filter::Filter;
+pub use gaps::Gaps;
+pub use index::{Index, Item};
+pub use journal::{undo, Entry, Journal, Stamp};
+pub use layout::{Layout, LayoutError};
 pub use organiser::Organiser;
+pub use overrides::{Overrides, Rule};
+pub use plan::{Plan, Step};
+pub use planner::Planner;
+pub use preserve::{parse_group, parse_mode, parse_user, Attributes, Preserve};
+pub use report::{Counts, Outcome, Record, Report};
 pub use settings::Settings;
+pub use transfer::Transfer;
@@ -0,0 +1,192 @@
+use std::fmt::Display;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+
+use crate::organise::transfer;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+/// What to do when an organised file already exists in the output directory.
+pub enum Conflict {
+    /// Keep the existing file and skip the new one.
+    #[default]
+    Skip,
+    /// Replace the existing file with the new one.
+    Overwrite,
+    /// Keep both, adding a numbered suffix to the new file.
+    KeepBoth,
+    /// Keep whichever file is larger.
+    KeepLarger,
+    /// Keep whichever file was modified most recently.
+    KeepNewer,
+    /// Skip the new file if it is identical, otherwise keep both.
+    SkipIdentical,
+    /// Keep whichever file was modified most recently, and put the other in the conflicts directory.
+    MoveToConflicts,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// The outcome of resolving a conflict.
+pub enum Resolution {
+    /// Skip the new file.
+    Skip,
+    /// Replace the existing file.
+    Overwrite,
+    /// Write the new file to a different path.
+    Rename(PathBuf),
+    /// Move the existing file to a different path, then write the new file.
+    Displace(PathBuf),
+}
+
+impl Conflict {
+    /// Directory inside the output directory for files moved by [`Conflict::MoveToConflicts`].
+    pub const DIR: &'static str = "conflicts";
+
+    /// Resolve a conflict between the source file and the existing file.
+    ///
+    /// The conflicts directory is only used by [`Conflict::MoveToConflicts`].
+    pub fn resolve(
+        self,
+        source: &Path,
+        existing: &Path,
+        conflicts: &Path,
+    ) -> io::Result<Resolution> {
+        let resolution = match self {
+            Self::Skip => Resolution::Skip,
+            Self::Overwrite => Resolution::Overwrite,
+            Self::KeepBoth => Resolution::Rename(numbered(existing)),
+            Self::KeepLarger => match source.metadata()?.len() > existing.metadata()?.len() {
+                true => Resolution::Overwrite,
+                false => Resolution::Skip,
+            },
+            Self::KeepNewer => {
+                match source.metadata()?.modified()? > existing.metadata()?.modified()? {
+                    true => Resolution::Overwrite,
+                    false => Resolution::Skip,
+                }
+            }
+            Self::SkipIdentical => {
+                let identical = source.metadata()?.len() == existing.metadata()?.len()
+                    && transfer::checksum(source)? == transfer::checksum(existing)?;
+
+                match identical {
+                    true => Resolution::Skip,
+                    false => Resolution::Rename(numbered(existing)),
+                }
+            }
+            Self::MoveToConflicts => {
+                let name = existing.file_name().unwrap_or_default();
+                let moved = numbered(&conflicts.join(name));
+
+                match source.metadata()?.modified()? > existing.metadata()?.modified()? {
+                    true => Resolution::Displace(moved),
+                    false => Resolution::Rename(moved),
+                }
+            }
+        };
+
+        log::trace!("resolved conflict with {self}: {:?}", resolution);
+
+        Ok(resolution)
+    }
+}
+
+impl Display for Conflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Skip => write!(f, "skip"),
+            Self::Overwrite => write!(f, "overwrite"),
+            Self::KeepBoth => write!(f, "keep both"),
+            Self::KeepLarger => write!(f, "keep larger"),
+            Self::KeepNewer => write!(f, "keep newer"),
+            Self::SkipIdentical => write!(f, "skip identical"),
+            Self::MoveToConflicts => write!(f, "move to conflicts"),
+        }
+    }
+}
+
+/// Get the first path that does not exist, adding a numbered suffix like ` (copy 2)` to the name.
+///
+/// The suffix is not parsed as a tag, so the copy is organised like the original.
+/// Returns the path unchanged if it does not exist.
+fn numbered(path: &Path) -> PathBuf {
+    if !path.exists() {
+        return path.to_path_buf();
+    }
+
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+
+    (2..)
+        .map(|n| path.with_file_name(format!("{stem} (copy {n}){extension}")))
+        .find(|path| !path.exists())
+        .expect("should find an unused numbered path")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use temp_dir::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_resolve() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = TempDir::new()?;
+        let conflicts = dir.child(Conflict::DIR);
+
+        let source = dir.child("source.cbz");
+        let existing = dir.child("Test 001.cbz");
+        fs::write(&existing, "existing")?;
+        fs::write(&source, "larger source")?;
+        fs::write(dir.child("Test 001 (copy 2).cbz"), "taken")?;
+
+        let hour_ago = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+        fs::File::options()
+            .write(true)
+            .open(&existing)?
+            .set_modified(hour_ago)?;
+
+        let numbered = dir.child("Test 001 (copy 3).cbz");
+
+        let cases = [
+            (Conflict::Skip, Resolution::Skip),
+            (Conflict::Overwrite, Resolution::Overwrite),
+            (Conflict::KeepBoth, Resolution::Rename(numbered.clone())),
+            (Conflict::KeepLarger, Resolution::Overwrite),
+            (Conflict::KeepNewer, Resolution::Overwrite),
+            (Conflict::SkipIdentical, Resolution::Rename(numbered)),
+            (
+                Conflict::MoveToConflicts,
+                Resolution::Displace(conflicts.join("Test 001.cbz")),
+            ),
+        ];
+
+        for (conflict, expected) in cases {
+            let resolution = conflict.resolve(&source, &existing, &conflicts)?;
+            assert_eq!(resolution, expected, "{conflict} should resolve");
+        }
+
+        fs::write(&source, "existing")?;
+        let resolution = Conflict::SkipIdentical.resolve(&source, &existing, &conflicts)?;
+        assert_eq!(resolution, Resolution::Skip, "identical file should skip");
+
+        fs::File::options()
+            .write(true)
+            .open(&source)?
+            .set_modified(hour_ago - std::time::Duration::from_secs(3600))?;
+        let resolution = Conflict::MoveToConflicts.resolve(&source, &existing, &conflicts)?;
+        assert_eq!(
+            resolution,
+            Resolution::Rename(conflicts.join("Test 001.cbz")),
+            "older new file should be put in the conflicts dir"
+        );
+
+        Ok(())
+    }
+}
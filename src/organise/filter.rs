@@ -0,0 +1,74 @@
+use std::path::Path;
+
+use globset::{Glob, GlobMatcher};
+
+#[derive(Clone, Debug, Default)]
+/// Include and exclude glob patterns for entries found while walking directories.
+///
+/// Patterns are matched against the path relative to the directory being organised,
+/// so `*.cbz` matches files at any depth and `extras/**` only matches below `extras`.
+pub struct Filter {
+    /// Files must match one of these patterns, if any are provided.
+    include: Vec<GlobMatcher>,
+    /// Files and directories matching any of these patterns are skipped.
+    exclude: Vec<GlobMatcher>,
+}
+
+impl Filter {
+    /// Create a new filter from the provided include and exclude patterns.
+    pub fn new(include: &[Glob], exclude: &[Glob]) -> Filter {
+        Filter {
+            include: include.iter().map(Glob::compile_matcher).collect(),
+            exclude: exclude.iter().map(Glob::compile_matcher).collect(),
+        }
+    }
+
+    /// Whether the entry at the provided relative path should be organised.
+    ///
+    /// Include patterns only apply to files, so directories are still walked.
+    pub fn matches(&self, path: &Path, is_dir: bool) -> bool {
+        if self.exclude.iter().any(|glob| glob.is_match(path)) {
+            return false;
+        }
+
+        is_dir || self.include.is_empty() || self.include.iter().any(|glob| glob.is_match(path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() -> Result<(), Box<dyn std::error::Error>> {
+        let filter = Filter::new(
+            &["*.cb?".parse()?],
+            &["*.part".parse()?, "extras".parse()?, "Thumbs.db".parse()?],
+        );
+
+        let cases = [
+            ("Test 001.cbz", false, true),
+            ("sub/Test 001.cbr", false, true),
+            ("Test 001.cbz.part", false, false),
+            ("Test 001.nfo", false, false),
+            ("Thumbs.db", false, false),
+            ("sub", true, true),
+            ("extras", true, false),
+        ];
+
+        for (path, is_dir, expected) in cases {
+            assert_eq!(
+                filter.matches(Path::new(path), is_dir),
+                expected,
+                "{path} should match: {expected}"
+            );
+        }
+
+        assert!(
+            Filter::default().matches(Path::new("Test 001.nfo"), false),
+            "default filter should match everything"
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,289 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Display;
+use std::io;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::comic::Meta;
+use crate::organise::journal::absolute;
+use crate::organise::Index;
+use crate::temp;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+/// Missing issues in a single run of a series.
+///
+/// Only whole, non-negative issue numbers count towards a run,
+/// so specials like `0.5` or `½` never leave gaps.
+pub struct Gaps {
+    /// Name of the series.
+    pub series: String,
+    /// Volume of the series, if it has been relaunched.
+    pub volume: Option<usize>,
+    /// Total number of issues for a limited series.
+    pub of: Option<usize>,
+    /// Number of distinct issues found.
+    pub found: usize,
+    /// Issue numbers missing from the run.
+    pub missing: Vec<usize>,
+}
+
+impl Gaps {
+    /// Find the gaps in each run of the series in the provided metadata.
+    ///
+    /// Runs start at issue 1 and end at the highest issue found, or at the total
+    /// for a limited series. Series names are matched ignoring case, and each run is
+    /// reported with the first name found. Only runs with missing issues are returned,
+    /// ordered by series.
+    pub fn find(metas: impl IntoIterator<Item = Meta>) -> Vec<Gaps> {
+        let mut runs: BTreeMap<(String, Option<usize>), Run> = BTreeMap::new();
+
+        for meta in metas {
+            let key = (meta.series.to_lowercase(), meta.volume);
+            let run = runs.entry(key).or_insert_with(|| Run {
+                series: meta.series.clone(),
+                of: None,
+                issues: BTreeSet::new(),
+            });
+
+            run.of = run.of.max(meta.of);
+
+            if let Some(number) = meta.number.filter(|n| !n.negative && n.fraction.is_none()) {
+                run.issues.extend(number.whole);
+            }
+        }
+
+        runs.into_iter()
+            .filter_map(|((_, volume), run)| {
+                let last = run.of.or(run.issues.last().copied())?;
+                let missing: Vec<usize> = (1..=last).filter(|n| !run.issues.contains(n)).collect();
+
+                if missing.is_empty() {
+                    return None;
+                }
+
+                Some(Gaps {
+                    series: run.series,
+                    volume,
+                    of: run.of,
+                    found: run.issues.len(),
+                    missing,
+                })
+            })
+            .collect()
+    }
+
+    /// Scan an organised library for gaps in each series.
+    ///
+    /// Files in the library index use the metadata they were organised with,
+    /// other files have their names parsed.
+    /// Hidden entries, such as the journal and index, and unparseable files are ignored.
+    pub fn scan(dir: &Path) -> io::Result<Vec<Gaps>> {
+        let index = Index::load(dir)?;
+        let indexed: HashMap<&Path, &Meta> = index
+            .files()
+            .map(|item| (item.destination.as_path(), &item.meta))
+            .collect();
+
+        let mut metas = vec![];
+        let mut dirs = vec![dir.to_path_buf()];
+
+        while let Some(dir) = dirs.pop() {
+            for entry in dir.read_dir()? {
+                let entry = entry?;
+                let path = entry.path();
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+
+                if name.starts_with('.') || temp::is_temp(&path) {
+                    log::trace!("skipping hidden entry: {}", path.display());
+                    continue;
+                }
+
+                if entry.file_type()?.is_dir() {
+                    dirs.push(path);
+                    continue;
+                }
+
+                if let Some(meta) = indexed.get(absolute(&path).as_path()) {
+                    metas.push((*meta).clone());
+                    continue;
+                }
+
+                match name.parse::<Meta>() {
+                    Ok(meta) => metas.push(meta),
+                    Err(err) => log::debug!("skipping unparseable file: {}: {err}", path.display()),
+                }
+            }
+        }
+
+        log::debug!("scanned {} files in: {}", metas.len(), dir.display());
+
+        Ok(Gaps::find(metas))
+    }
+}
+
+impl Display for Gaps {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.series)?;
+
+        if let Some(volume) = self.volume {
+            write!(f, " v{volume}")?;
+        }
+
+        write!(f, ": missing ")?;
+
+        let mut missing = self.missing.iter().copied().peekable();
+        let mut first = true;
+
+        while let Some(start) = missing.next() {
+            let mut end = start;
+            while missing.next_if_eq(&(end + 1)).is_some() {
+                end += 1;
+            }
+
+            if !first {
+                write!(f, ", ")?;
+            }
+            first = false;
+
+            match start == end {
+                true => write!(f, "{start}")?,
+                false => write!(f, "{start}-{end}")?,
+            }
+        }
+
+        match self.of {
+            Some(of) => write!(f, " (incomplete, {} of {of})", self.found),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Issues found in a single run of a series.
+struct Run {
+    /// Name of the series, as first found.
+    series: String,
+    /// Total number of issues for a limited series.
+    of: Option<usize>,
+    /// Whole issue numbers found.
+    issues: BTreeSet<usize>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use temp_dir::TempDir;
+
+    use super::*;
+    use crate::organise::{Item, Stamp, Transfer};
+
+    #[test]
+    fn test_find() -> Result<(), Box<dyn std::error::Error>> {
+        let names = [
+            "Batman 001 (2020).cbz",
+            "Batman 002 (2020).cbz",
+            "Batman 005 (2020).cbz",
+            "Batman 006 (2020).cbz",
+            "Batman 008 (2020).cbz",
+            "Batman 003.5 (2020).cbz",
+            "Saga v2 001 (2021).cbz",
+            "Saga v2 002 (2021).cbz",
+            "Watchmen 001 of 12 (1986).cbz",
+            "Watchmen 003 of 12 (1986).cbz",
+            "Complete 001 of 2 (2000).cbz",
+            "Complete 002 of 2 (2000).cbz",
+        ];
+
+        let metas = names
+            .iter()
+            .map(|name| name.parse::<Meta>())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let gaps = Gaps::find(metas);
+
+        assert_eq!(
+            gaps,
+            [
+                Gaps {
+                    series: "Batman".into(),
+                    volume: None,
+                    of: None,
+                    found: 5,
+                    missing: vec![3, 4, 7],
+                },
+                Gaps {
+                    series: "Watchmen".into(),
+                    volume: None,
+                    of: Some(12),
+                    found: 2,
+                    missing: vec![2, 4, 5, 6, 7, 8, 9, 10, 11, 12],
+                },
+            ],
+            "only runs with missing issues should be reported"
+        );
+
+        assert_eq!(gaps[0].to_string(), "Batman: missing 3-4, 7");
+        assert_eq!(
+            gaps[1].to_string(),
+            "Watchmen: missing 2, 4-12 (incomplete, 2 of 12)"
+        );
+
+        let metas = ["Saga 001.cbz", "SAGA 003.cbz", "saga 004.cbz"]
+            .iter()
+            .map(|name| name.parse::<Meta>())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        assert_eq!(
+            Gaps::find(metas)
+                .iter()
+                .map(|gaps| gaps.to_string())
+                .collect::<Vec<_>>(),
+            ["Saga: missing 2"],
+            "series should be matched ignoring case and keep the first name"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = TempDir::new()?;
+        let series_dir = dir.child("Test");
+        let hidden_dir = dir.child(".cyborg");
+
+        fs::create_dir_all(&series_dir)?;
+        fs::create_dir_all(&hidden_dir)?;
+        fs::write(series_dir.join("Test 001.cbz"), "")?;
+        fs::write(series_dir.join("Test 003.cbz"), "")?;
+        fs::write(series_dir.join("notes.txt"), "")?;
+        fs::write(hidden_dir.join("Test 002.cbz"), "")?;
+
+        let gaps = Gaps::scan(dir.path())?;
+
+        assert_eq!(gaps.len(), 1, "should find one run with gaps");
+        assert_eq!(gaps[0].missing, [2], "hidden files should be ignored");
+
+        let scanned = series_dir.join("scan.cbz");
+        fs::write(&scanned, "")?;
+
+        let mut index = Index::load(dir.path())?;
+        index.insert(Item {
+            source: dir.child("scan.cbz"),
+            source_stamp: Stamp::of(&scanned)?,
+            destination: absolute(&scanned),
+            stamp: Stamp::of(&scanned)?,
+            hash: "00".into(),
+            meta: "Test 002.cbz".parse()?,
+            transfer: Transfer::Copy,
+        });
+        index.save(dir.path())?;
+
+        let gaps = Gaps::scan(dir.path())?;
+
+        assert!(gaps.is_empty(), "indexed files should use their metadata");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,227 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::comic::Meta;
+use crate::organise::{Stamp, Transfer};
+use crate::temp;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+/// A file in the library index.
+pub struct Item {
+    /// The file that was organised.
+    pub source: PathBuf,
+    /// Stamp of the source when it was organised, used to skip it while it is unchanged.
+    pub source_stamp: Stamp,
+    /// Where the file was organised to.
+    pub destination: PathBuf,
+    /// Stamp of the organised file.
+    pub stamp: Stamp,
+    /// SHA-256 checksum of the organised file, as hex.
+    pub hash: String,
+    /// Metadata the file was organised with.
+    pub meta: Meta,
+    /// How the file was asked to be transferred, so it is organised again with another mode.
+    #[serde(default)]
+    pub transfer: Transfer,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+/// Index of organised files, kept in the output directory between runs.
+///
+/// Files are keyed by their absolute destination. Paths are stored absolute,
+/// so the index stays valid when organising from another directory.
+pub struct Index {
+    /// Version of the index format.
+    version: u32,
+    /// Indexed files by destination.
+    files: BTreeMap<PathBuf, Item>,
+    /// Destinations by source, for looking up inputs.
+    #[serde(skip)]
+    sources: HashMap<PathBuf, PathBuf>,
+    /// Whether the index has changed since it was loaded.
+    #[serde(skip)]
+    changed: bool,
+}
+
+impl Index {
+    /// Path of the index inside the output directory.
+    pub const PATH: &'static str = ".cyborg/index.json";
+
+    /// Current version of the index format.
+    const VERSION: u32 = 1;
+
+    /// Load the index from the provided output directory.
+    ///
+    /// Returns an empty index if there is none yet. Files that are no longer
+    /// at their destination, such as after an undo, are dropped.
+    pub fn load(output: &Path) -> io::Result<Index> {
+        let path = output.join(Self::PATH);
+
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                log::debug!("no index found: {}", path.display());
+                return Ok(Index {
+                    version: Self::VERSION,
+                    ..Default::default()
+                });
+            }
+            Err(err) => return Err(err),
+        };
+
+        let mut index: Index = serde_json::from_reader(BufReader::new(file))?;
+
+        if index.version != Self::VERSION {
+            return Err(io::Error::other(format!(
+                "unsupported index version {}: {}",
+                index.version,
+                path.display()
+            )));
+        }
+
+        let count = index.files.len();
+        index.files.retain(|destination, _| destination.exists());
+
+        if index.files.len() != count {
+            log::debug!(
+                "dropped {} missing files from index",
+                count - index.files.len()
+            );
+            index.changed = true;
+        }
+
+        index.sources = index
+            .files
+            .values()
+            .map(|item| (item.source.clone(), item.destination.clone()))
+            .collect();
+
+        log::debug!("loaded {} files from index", index.files.len());
+
+        Ok(index)
+    }
+
+    /// Save the index to the provided output directory, if it has changed.
+    ///
+    /// The index is written to a temporary file and renamed into place.
+    pub fn save(&mut self, output: &Path) -> io::Result<()> {
+        if !self.changed {
+            return Ok(());
+        }
+
+        let path = output.join(Self::PATH);
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let temp = temp::path(&path);
+
+        let result = File::create(&temp).and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            serde_json::to_writer(&mut writer, self)?;
+            writer.flush()?;
+            writer.get_ref().sync_data()
+        });
+
+        if let Err(err) = result.and_then(|_| fs::rename(&temp, &path)) {
+            let _ = fs::remove_file(&temp);
+            return Err(err);
+        }
+
+        log::debug!("saved {} files to index", self.files.len());
+        self.changed = false;
+
+        Ok(())
+    }
+
+    /// Get the indexed file organised from the source with the provided transfer,
+    /// if the source has not changed since.
+    pub fn unchanged(&self, source: &Path, stamp: &Stamp, transfer: Transfer) -> Option<&Item> {
+        let destination = self.sources.get(source)?;
+
+        self.files.get(destination).filter(|item| {
+            item.source_stamp == *stamp && item.transfer == transfer && destination.exists()
+        })
+    }
+
+    /// Add an organised file to the index, replacing any file at the same destination.
+    pub fn insert(&mut self, item: Item) {
+        if let Some(replaced) = self.files.get(&item.destination) {
+            self.sources.remove(&replaced.source);
+        }
+
+        self.sources
+            .insert(item.source.clone(), item.destination.clone());
+        self.files.insert(item.destination.clone(), item);
+        self.changed = true;
+    }
+
+    /// Iterate over the indexed files, ordered by destination.
+    pub fn files(&self) -> impl Iterator<Item = &Item> {
+        self.files.values()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use temp_dir::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_save_and_load() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = TempDir::new()?;
+        let source = dir.child("Test 001 (2020).cbz");
+        let destination = dir.child("Test").join("Test 001 (2020).cbz");
+        fs::write(&source, "contents")?;
+        fs::create_dir_all(destination.parent().unwrap_or(dir.path()))?;
+        fs::write(&destination, "contents")?;
+
+        let stamp = Stamp::of(&source)?;
+
+        let mut index = Index::load(dir.path())?;
+        assert_eq!(index.files().count(), 0, "new index should be empty");
+
+        index.insert(Item {
+            source: source.clone(),
+            source_stamp: stamp.clone(),
+            destination: destination.clone(),
+            stamp: Stamp::of(&destination)?,
+            hash: "00".into(),
+            meta: "Test 001 (2020).cbz".parse()?,
+            transfer: Transfer::Copy,
+        });
+        index.save(dir.path())?;
+
+        let index = Index::load(dir.path())?;
+        assert_eq!(index.files().count(), 1, "should load saved files");
+        assert!(
+            index.unchanged(&source, &stamp, Transfer::Copy).is_some(),
+            "unchanged source should be found"
+        );
+
+        let changed = Stamp {
+            size: stamp.size + 1,
+            ..stamp.clone()
+        };
+        assert!(
+            index.unchanged(&source, &changed, Transfer::Copy).is_none(),
+            "changed source should not be found"
+        );
+        assert!(
+            index.unchanged(&source, &stamp, Transfer::Move).is_none(),
+            "source organised with another transfer should not be found"
+        );
+
+        fs::remove_file(&destination)?;
+        let index = Index::load(dir.path())?;
+        assert_eq!(index.files().count(), 0, "missing files should be dropped");
+
+        Ok(())
+    }
+}
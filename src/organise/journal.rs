@@ -0,0 +1,423 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::organise::transfer;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+/// Size and modification time of a file, used to detect later changes.
+pub struct Stamp {
+    /// Size of the file in bytes.
+    pub size: u64,
+    /// Last modification time of the file.
+    pub modified: Option<SystemTime>,
+}
+
+impl Stamp {
+    /// Get the stamp of the file at the provided path.
+    pub fn of(path: &Path) -> io::Result<Stamp> {
+        let metadata = path.metadata()?;
+
+        Ok(Stamp {
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "kebab-case")]
+/// A single change made to the filesystem while organising.
+pub enum Entry {
+    /// A directory was created.
+    CreateDir { path: PathBuf },
+    /// An empty directory was removed.
+    RemoveDir { path: PathBuf },
+    /// A file was copied to the destination.
+    Copy {
+        source: PathBuf,
+        destination: PathBuf,
+        stamp: Stamp,
+    },
+    /// A file was moved to the destination.
+    Move {
+        source: PathBuf,
+        destination: PathBuf,
+        stamp: Stamp,
+    },
+    /// A symlink to the source was created at the destination.
+    Link {
+        source: PathBuf,
+        destination: PathBuf,
+    },
+    /// A file was converted to the destination.
+    Convert {
+        source: PathBuf,
+        destination: PathBuf,
+        stamp: Stamp,
+    },
+    /// An existing file was moved to a backup before being overwritten.
+    Overwrite { path: PathBuf, backup: PathBuf },
+    /// A file was moved to a backup instead of being removed.
+    Remove { path: PathBuf, backup: PathBuf },
+    /// A file was changed in place after a backup was made.
+    Modify {
+        path: PathBuf,
+        backup: PathBuf,
+        stamp: Stamp,
+    },
+}
+
+/// Journal of the changes made during a single run.
+///
+/// Entries are appended as JSON lines as each change is made,
+/// so the journal survives an interrupted run.
+pub struct Journal {
+    /// Path of the journal file.
+    path: PathBuf,
+    /// Directory holding backups of overwritten and removed files.
+    backups: PathBuf,
+    /// The open journal file.
+    file: File,
+    /// Number of backups made, used to name them.
+    count: usize,
+}
+
+impl Journal {
+    /// Directory inside the output directory where journals are kept.
+    pub const DIR: &'static str = ".cyborg/journal";
+
+    /// Extension of journal files.
+    const EXTENSION: &'static str = "jsonl";
+
+    /// Extension of journal files that have been undone.
+    const UNDONE: &'static str = "undone";
+
+    /// Create a new journal for a run in the provided output directory.
+    pub fn create(output: &Path) -> io::Result<Journal> {
+        let dir = std::path::absolute(output.join(Self::DIR))?;
+        fs::create_dir_all(&dir)?;
+
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+
+        let path = dir.join(format!("{millis:016}.{}", Self::EXTENSION));
+        let backups = dir.join(format!("{millis:016}"));
+
+        let file = OpenOptions::new()
+            .create_new(true)
+            .append(true)
+            .open(&path)?;
+
+        log::debug!("created journal: {}", path.display());
+
+        Ok(Journal {
+            path,
+            backups,
+            file,
+            count: 0,
+        })
+    }
+
+    /// Get the path of the journal file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append an entry to the journal.
+    pub fn record(&mut self, entry: &Entry) -> io::Result<()> {
+        log::trace!("recording journal entry: {:?}", entry);
+        let line = serde_json::to_string(entry)?;
+        writeln!(self.file, "{line}")?;
+        self.file.sync_data()
+    }
+
+    /// Get a new path in the backups directory for the provided file.
+    pub fn backup_path(&mut self, path: &Path) -> io::Result<PathBuf> {
+        fs::create_dir_all(&self.backups)?;
+        self.count += 1;
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        Ok(self.backups.join(format!("{:06}-{name}", self.count)))
+    }
+
+    /// Get the most recent journal in the provided output directory that has not been undone.
+    pub fn latest(output: &Path) -> io::Result<Option<PathBuf>> {
+        let dir = output.join(Self::DIR);
+
+        if !dir.exists() {
+            return Ok(None);
+        }
+
+        let mut journals = vec![];
+
+        for entry in dir.read_dir()? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == Self::EXTENSION) {
+                journals.push(path);
+            }
+        }
+
+        Ok(journals.into_iter().max())
+    }
+
+    /// Read all entries from the journal at the provided path.
+    pub fn read(path: &Path) -> io::Result<Vec<Entry>> {
+        let file = File::open(path)?;
+        let mut entries = vec![];
+
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                entries.push(serde_json::from_str(&line)?);
+            }
+        }
+
+        Ok(entries)
+    }
+}
+
+/// Undo the changes recorded in the journal at the provided path.
+///
+/// Entries are undone in reverse order. Files that have changed since
+/// they were organised are refused and kept in the journal, so the
+/// remaining entries can be undone later. Once every entry is undone
+/// the journal is marked as undone.
+pub fn undo(path: &Path, dry_run: bool) -> anyhow::Result<()> {
+    log::debug!("undoing journal: {}", path.display());
+
+    let entries = Journal::read(path).context("reading journal")?;
+    let mut failed = vec![];
+
+    for entry in entries.into_iter().rev() {
+        if let Err(err) = undo_entry(&entry, dry_run) {
+            log::error!("failed to undo entry: {err:#}");
+            failed.push(entry);
+        }
+    }
+
+    if dry_run {
+        return Ok(());
+    }
+
+    if failed.is_empty() {
+        // Every backup has been restored, so anything left over was never recorded.
+        let backups = path.with_extension("");
+        if backups.exists() {
+            if let Err(err) = fs::remove_dir_all(&backups) {
+                log::warn!("failed to remove backups dir: {}: {err}", backups.display());
+            }
+        }
+
+        let undone = path.with_extension(format!("{}.{}", Journal::EXTENSION, Journal::UNDONE));
+        fs::rename(path, undone).context("marking journal as undone")?;
+
+        log::debug!("undone journal: {}", path.display());
+
+        return Ok(());
+    }
+
+    let mut file = File::create(path).context("rewriting journal")?;
+    for entry in failed.iter().rev() {
+        let line = serde_json::to_string(entry)?;
+        writeln!(file, "{line}").context("rewriting journal")?;
+    }
+
+    anyhow::bail!("failed to undo {} journal entries", failed.len())
+}
+
+/// Undo a single journal entry.
+fn undo_entry(entry: &Entry, dry_run: bool) -> anyhow::Result<()> {
+    match entry {
+        Entry::CreateDir { path } if !path.exists() => {
+            log::debug!("dir already removed: {}", path.display());
+        }
+        Entry::CreateDir { path } => {
+            let empty = path
+                .read_dir()
+                .with_context(|| format!("reading dir: {}", path.display()))?
+                .next()
+                .is_none();
+
+            match [empty, dry_run] {
+                [_, true] => log::info!("would remove dir: {}", path.display()),
+                [false, false] => anyhow::bail!("dir is not empty: {}", path.display()),
+                [true, false] => {
+                    log::info!("removing dir: {}", path.display());
+                    fs::remove_dir(path).context("removing dir")?;
+                }
+            }
+        }
+        Entry::RemoveDir { path } => match dry_run {
+            true => log::info!("would recreate dir: {}", path.display()),
+            false => {
+                log::info!("recreating dir: {}", path.display());
+                fs::create_dir_all(path).context("recreating dir")?;
+            }
+        },
+        Entry::Copy {
+            destination, stamp, ..
+        }
+        | Entry::Convert {
+            destination, stamp, ..
+        } => {
+            verify(destination, stamp)?;
+
+            match dry_run {
+                true => log::info!("would remove: {}", destination.display()),
+                false => {
+                    log::info!("removing: {}", destination.display());
+                    fs::remove_file(destination).context("removing file")?;
+                }
+            }
+        }
+        Entry::Link { destination, .. } => {
+            let is_symlink = destination
+                .symlink_metadata()
+                .with_context(|| format!("link is missing: {}", destination.display()))?
+                .is_symlink();
+
+            match [is_symlink, dry_run] {
+                [false, _] => anyhow::bail!("link has been replaced: {}", destination.display()),
+                [true, true] => log::info!("would remove link: {}", destination.display()),
+                [true, false] => {
+                    log::info!("removing link: {}", destination.display());
+                    fs::remove_file(destination).context("removing link")?;
+                }
+            }
+        }
+        Entry::Move {
+            source,
+            destination,
+            stamp,
+        } => {
+            verify(destination, stamp)?;
+            restore(destination, source, dry_run)?;
+        }
+        Entry::Overwrite { path, backup } | Entry::Remove { path, backup } => {
+            if path.exists() {
+                anyhow::bail!("path already exists: {}", path.display());
+            }
+
+            restore(backup, path, dry_run)?;
+        }
+        Entry::Modify {
+            path,
+            backup,
+            stamp,
+        } => {
+            verify(path, stamp)?;
+            restore(backup, path, dry_run)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Check that the file at the provided path has not changed since it was stamped.
+fn verify(path: &Path, stamp: &Stamp) -> anyhow::Result<()> {
+    let current =
+        Stamp::of(path).with_context(|| format!("file is missing: {}", path.display()))?;
+
+    if current != *stamp {
+        anyhow::bail!(
+            "file has changed since it was organised: {}",
+            path.display()
+        );
+    }
+
+    Ok(())
+}
+
+/// Move a file back to its original path.
+fn restore(from: &Path, to: &Path, dry_run: bool) -> anyhow::Result<()> {
+    if dry_run {
+        log::info!("would restore: {} -> {}", from.display(), to.display());
+        return Ok(());
+    }
+
+    log::info!("restoring: {} -> {}", from.display(), to.display());
+
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).context("creating parent dir")?;
+    }
+
+    transfer::move_file(from, to).context("restoring file")
+}
+
+/// Get the absolute form of the provided path for recording in a journal.
+pub(crate) fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use temp_dir::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_record_and_read() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = TempDir::new()?;
+
+        let file = dir.child("file");
+        fs::write(&file, "contents")?;
+
+        let entries = [
+            Entry::CreateDir {
+                path: dir.child("created"),
+            },
+            Entry::Copy {
+                source: dir.child("source"),
+                destination: file.clone(),
+                stamp: Stamp::of(&file)?,
+            },
+        ];
+
+        let mut journal = Journal::create(dir.path())?;
+        for entry in &entries {
+            journal.record(entry)?;
+        }
+
+        let latest = Journal::latest(dir.path())?;
+        assert_eq!(latest.as_deref(), Some(journal.path()));
+        assert_eq!(Journal::read(journal.path())?, entries);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_undo_refuses_changed_files() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = TempDir::new()?;
+
+        let source = dir.child("source");
+        let destination = dir.child("destination");
+        fs::write(&source, "contents")?;
+        fs::copy(&source, &destination)?;
+
+        let mut journal = Journal::create(dir.path())?;
+        journal.record(&Entry::Copy {
+            source: source.clone(),
+            destination: destination.clone(),
+            stamp: Stamp::of(&destination)?,
+        })?;
+
+        fs::write(&destination, "changed contents")?;
+
+        undo(journal.path(), false).expect_err("should refuse changed file");
+
+        assert!(destination.exists(), "changed file should be kept");
+        assert_eq!(
+            Journal::read(journal.path())?.len(),
+            1,
+            "refused entry should be kept in the journal"
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use crate::comic::{Meta, Template, TemplateError};
+
+#[derive(Debug, Error)]
+/// Errors that can occur when parsing a layout.
+pub enum LayoutError {
+    #[error(transparent)]
+    ParseTemplate(#[from] TemplateError),
+    #[error("layout must be a relative path")]
+    Absolute,
+    #[error("layout must not contain empty, `.` or `..` components")]
+    Component,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Directory layout for organised files, relative to the output directory.
+///
+/// This is a [`Template`] where each `/` separates a directory.
+pub struct Layout(Template);
+
+impl Layout {
+    /// Layout used when organising files by series.
+    pub const SERIES: &'static str = "{series}[/v{volume}]";
+
+    /// Create a layout for organising files by series and volume.
+    pub fn series() -> Layout {
+        Self::SERIES.parse().expect("series layout should be valid")
+    }
+
+    /// Render the directory for the provided metadata.
+    pub fn render(&self, meta: &Meta) -> PathBuf {
+        self.0.render_path(meta)
+    }
+}
+
+impl Display for Layout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for Layout {
+    type Err = LayoutError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.starts_with('/') {
+            return Err(LayoutError::Absolute);
+        }
+
+        let components = s.strip_suffix('/').unwrap_or(s).split('/');
+
+        if components.into_iter().any(|c| matches!(c, "" | "." | "..")) {
+            return Err(LayoutError::Component);
+        }
+
+        Template::parse_path(s)
+            .map(Self)
+            .map_err(LayoutError::ParseTemplate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_errors() {
+        for layout in [
+            "/{series}",
+            "{series}//{title}",
+            "../{series}",
+            "./{series}",
+            "",
+        ] {
+            assert!(layout.parse::<Layout>().is_err(), "should reject: {layout}");
+        }
+
+        for layout in [
+            "{series}/",
+            "{format}/{series}/vol{volume}",
+            "[{title}/]{series}",
+        ] {
+            assert!(layout.parse::<Layout>().is_ok(), "should accept: {layout}");
+        }
+    }
+
+    #[test]
+    fn test_render_stays_inside() -> Result<(), Box<dyn std::error::Error>> {
+        let layout: Layout = "{series}/{title}[/{volume}]".parse()?;
+
+        for name in [".. 002.cbz", "Batman 001 ...cbz", ". 001 (2020).cbz"] {
+            let meta: Meta = name.parse()?;
+            let dir = layout.render(&meta);
+
+            assert!(
+                dir.components()
+                    .all(|component| matches!(component, std::path::Component::Normal(_))),
+                "layout should only render normal components: {name} -> {}",
+                dir.display()
+            );
+        }
+
+        let meta: Meta = ".. 002.cbz".parse()?;
+        assert_eq!(Layout::series().render(&meta), PathBuf::from("__"));
+
+        Ok(())
+    }
+}
@@ -1,34 +1,235 @@
+use std::collections::HashSet;
 use std::fs;
+use std::io;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
 
 use anyhow::Context;
+use filetime::FileTime;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-
-use crate::{comic::Meta, organise::Settings};
+use signal_hook::consts::{SIGINT, SIGTERM};
+
+use crate::comic::{convert_to_cbz, ComicInfo, Format, Meta, MetaError};
+use crate::organise::journal::absolute;
+use crate::organise::prompt;
+use crate::organise::transfer;
+use crate::organise::watch::Watch;
+use crate::organise::{
+    Conflict, Entry, Index, Item, Journal, Outcome, Overrides, Plan, Planner, Record, Report,
+    Resolution, Rule, Settings, Stamp, Step, Transfer,
+};
+use crate::temp;
 
 #[derive(Default)]
 /// Organiser for organising comic files
+///
+/// Each file is planned with a [`Planner`], then its steps are applied, or collected
+/// without making changes on a dry run or when making a plan.
 pub struct Organiser {
     /// Settings for the organiser
     settings: Settings,
     /// The progress bar for the organiser
     multibar: MultiProgress,
+    /// The journal of changes made during this run, created on first use
+    journal: Mutex<Option<Journal>>,
+    /// The index of organised files, loaded on first use
+    index: Mutex<Option<Index>>,
+    /// The remembered answers for organising files, loaded on first use
+    overrides: Mutex<Option<Overrides>>,
+    /// Output directories already cleaned of leftover temporary files
+    cleaned: Mutex<HashSet<PathBuf>>,
+    /// Set when organising should stop after the current file
+    stop: Arc<AtomicBool>,
+}
+
+/// What happens to the steps planned for each file
+enum Mode {
+    /// Apply each step as soon as it is planned
+    Apply,
+    /// Only log each step and add it to the plan, on a dry run or when making a plan
+    Collect(Plan),
+}
+
+/// A single pass over the provided paths or steps
+struct Run {
+    /// What happens to the planned steps
+    mode: Mode,
+    /// The outcome of each file so far
+    report: Report,
+}
+
+impl Run {
+    /// Create a run that applies its steps, or collects them on a dry run
+    fn new(settings: &Settings) -> Run {
+        match settings.dry_run {
+            true => Run::collect(settings),
+            false => Run {
+                mode: Mode::Apply,
+                report: Report::new(false),
+            },
+        }
+    }
+
+    /// Create a run that collects its steps into a plan without making any changes
+    fn collect(settings: &Settings) -> Run {
+        Run {
+            mode: Mode::Collect(Plan::new(&settings.output)),
+            report: Report::new(true),
+        }
+    }
 }
 
 impl Organiser {
     /// Create a new Organiser instance with the provided settings
     pub fn new(settings: Settings, multibar: MultiProgress) -> Organiser {
-        Self { settings, multibar }
+        Self {
+            settings,
+            multibar,
+            ..Default::default()
+        }
+    }
+
+    /// Stop organising after the current file when SIGINT or SIGTERM is received
+    ///
+    /// A second signal while stopping exits immediately.
+    pub fn stop_on_signals(&self) -> anyhow::Result<()> {
+        for signal in [SIGINT, SIGTERM] {
+            signal_hook::flag::register_conditional_shutdown(signal, 1, Arc::clone(&self.stop))
+                .context("registering signal handler")?;
+            signal_hook::flag::register(signal, Arc::clone(&self.stop))
+                .context("registering signal handler")?;
+        }
+
+        Ok(())
+    }
+
+    /// Organise the provided paths, returning a report of the outcome of each file
+    ///
+    /// The index and overrides are saved once the paths have been organised,
+    /// even if organising fails.
+    pub fn organise(&self, paths: Vec<PathBuf>) -> anyhow::Result<Report> {
+        let mut run = Run::new(&self.settings);
+        let result = self.organise_paths(paths, &mut run);
+
+        if let Err(err) = self.save_index() {
+            log::error!("failed to save index: {err:#}");
+        }
+
+        if let Err(err) = self.save_overrides() {
+            log::error!("failed to save overrides: {err:#}");
+        }
+
+        result.map(|_| run.report)
+    }
+
+    /// Plan how to organise the provided paths, without making any changes
+    ///
+    /// Returns the plan along with a report of the outcome each file would have.
+    /// Files that cannot be organised are added to the plan as skipped with the reason.
+    /// Any answers given while planning are saved to the overrides.
+    pub fn plan(&self, paths: Vec<PathBuf>) -> anyhow::Result<(Plan, Report)> {
+        let mut run = Run::collect(&self.settings);
+        let result = self.organise_paths(paths, &mut run);
+
+        if let Err(err) = self.save_overrides() {
+            log::error!("failed to save overrides: {err:#}");
+        }
+
+        result?;
+
+        match run.mode {
+            Mode::Collect(plan) => Ok((plan, run.report)),
+            Mode::Apply => unreachable!("planning should collect steps"),
+        }
+    }
+
+    /// Apply the provided plan, returning a report of the outcome of each file
+    ///
+    /// Steps are applied in order. Conflicts are resolved again in case the output has
+    /// changed since the plan was made. The index is saved once the plan has been applied,
+    /// even if applying fails.
+    pub fn apply(&self, plan: Plan) -> anyhow::Result<Report> {
+        let mut run = Run::new(&self.settings);
+        let result = self.apply_steps(plan.steps, &mut run);
+
+        if let Err(err) = self.save_index() {
+            log::error!("failed to save index: {err:#}");
+        }
+
+        result.map(|_| run.report)
+    }
+
+    /// Apply the provided steps without saving the index
+    fn apply_steps(&self, steps: Vec<Step>, run: &mut Run) -> anyhow::Result<()> {
+        log::trace!("applying plan");
+
+        let style = progress_style()?;
+
+        let pb = self.multibar.add(ProgressBar::new(steps.len() as u64));
+        pb.set_style(style.clone());
+
+        match self.settings.dry_run {
+            true => pb.set_message("applying plan (dry run)"),
+            false => pb.set_message("applying plan"),
+        }
+
+        for step in steps {
+            if self.interrupted(run) {
+                break;
+            }
+
+            let source = step.record().map(|record| record.source);
+
+            let cpb = self.multibar.insert_after(&pb, ProgressBar::new(1));
+            cpb.set_style(style.clone());
+            cpb.set_prefix(format!("> {}", cpb.prefix()));
+
+            if let Some(name) = source.as_deref().and_then(Path::file_name) {
+                cpb.set_message(name.to_string_lossy().to_string());
+            }
+
+            let result = self.run_step(step, &cpb, run);
+
+            match result {
+                Ok(Some(record)) => run.report.add(record),
+                Ok(None) => cpb.finish_and_clear(),
+                Err(err) => {
+                    log::error!("failed to apply step: {err:#}");
+
+                    if let Some(source) = &source {
+                        report_outcome(
+                            run,
+                            source,
+                            None,
+                            Outcome::Failed,
+                            Some(format!("{err:#}")),
+                        );
+                    }
+
+                    if self.settings.exit {
+                        pb.abandon();
+                        return Err(err).context("failed to apply step");
+                    }
+                }
+            }
+
+            pb.inc(1);
+        }
+
+        pb.finish();
+
+        log::trace!("applied plan");
+
+        Ok(())
     }
 
-    /// Organise the provided paths
-    pub fn organise(&self, paths: Vec<PathBuf>) -> anyhow::Result<()> {
+    /// Organise the provided paths without saving the index
+    fn organise_paths(&self, paths: Vec<PathBuf>, run: &mut Run) -> anyhow::Result<()> {
         log::trace!("organising files");
 
-        let style = ProgressStyle::default_bar()
-            .template("{prefix}{msg} [{wide_bar}] [{pos}/{len}] [{duration}]")
-            .context("setting progress bar style")?
-            .progress_chars("=> ");
+        let style = progress_style()?;
 
         let pb = self.multibar.add(ProgressBar::new(paths.len() as u64));
         pb.set_style(style.clone());
@@ -39,11 +240,16 @@ impl Organiser {
         }
 
         for path in paths {
+            if self.interrupted(run) {
+                break;
+            }
+
             let result = path.metadata();
 
             if let Err(err) = result {
                 let message = format!("failed to get metadata for: {}", path.display());
                 log::error!("{message}: {err:#}");
+                report_outcome(run, &path, None, Outcome::Failed, Some(err.to_string()));
                 match self.settings.exit {
                     true => {
                         pb.abandon();
@@ -61,8 +267,14 @@ impl Organiser {
             cpb.set_prefix(format!("> {}", cpb.prefix()));
 
             let result = match meta.is_dir() {
-                true => self.organise_dir(&path, cpb.clone()),
-                false => self.organise_file(&path, cpb.clone()),
+                true => self
+                    .organise_dir(&path, &path, 0, cpb.clone(), run)
+                    .map(|_| ()),
+                false => {
+                    let root = path.parent().unwrap_or(Path::new(""));
+                    self.organise_file(&path, root, cpb.clone(), run)
+                        .map(|_| ())
+                }
             };
 
             if let Err(err) = result {
@@ -89,20 +301,119 @@ impl Organiser {
 }
 
 impl Organiser {
-    /// Organise the provided directory
-    fn organise_dir(&self, path: &Path, pb: ProgressBar) -> anyhow::Result<()> {
+    /// Watch the provided paths, organising new files once they stop changing
+    ///
+    /// Existing files are organised first. Watching stops cleanly after the current file
+    /// when stopped by a signal, see [`Organiser::stop_on_signals`].
+    ///
+    /// Returns a report of the outcome of each file organised while watching.
+    pub fn watch(&self, paths: Vec<PathBuf>) -> anyhow::Result<Report> {
+        // Watching starts first, so files added during the first pass are not missed.
+        // Files changed by the first pass are debounced, then skipped as already organised.
+        let mut watch = Watch::new(&paths, self.settings.recursive, self.settings.settle)
+            .context("watching paths")?;
+
+        let mut report = self.organise(paths.clone())?;
+
+        let style = progress_style()?;
+        let roots: Vec<PathBuf> = paths.iter().map(|path| absolute(path)).collect();
+        let mut run = Run::new(&self.settings);
+
+        log::info!("watching for new files");
+
+        while !self.stop.load(Ordering::Relaxed) {
+            for path in watch.ready(WATCH_POLL) {
+                if self.stop.load(Ordering::Relaxed) {
+                    break;
+                }
+
+                if temp::is_temp(&path) || self.is_state(&path) {
+                    log::trace!("skipping watched file: {}", path.display());
+                    continue;
+                }
+
+                let root = roots
+                    .iter()
+                    .find(|root| path.starts_with(root))
+                    .map(PathBuf::as_path)
+                    .or(path.parent())
+                    .unwrap_or(Path::new(""));
+                let relative = path.strip_prefix(root).unwrap_or(&path);
+
+                if !self.settings.filter.matches(relative, false) {
+                    log::debug!("skipping filtered path: {}", path.display());
+                    let reason = Some("filtered".into());
+                    report_outcome(&mut run, &path, None, Outcome::Skipped, reason);
+                    continue;
+                }
+
+                let pb = self.multibar.add(ProgressBar::new(1));
+                pb.set_style(style.clone());
+
+                if let Err(err) = self.organise_file(&path, root, pb, &mut run) {
+                    let message = format!("failed to organise watched file: {}", path.display());
+                    log::error!("{message}: {err:#}");
+                    if self.settings.exit {
+                        let _ = self.save_index();
+                        let _ = self.save_overrides();
+                        return Err(err).context(message);
+                    }
+                }
+            }
+
+            if let Err(err) = self.save_index() {
+                log::error!("failed to save index: {err:#}");
+            }
+
+            if let Err(err) = self.save_overrides() {
+                log::error!("failed to save overrides: {err:#}");
+            }
+        }
+
+        log::info!("stopped watching");
+
+        report.extend(run.report);
+
+        Ok(report)
+    }
+}
+
+impl Organiser {
+    /// Organise the provided directory, returning whether it was removed once emptied
+    ///
+    /// Entries are filtered relative to the root directory before any progress bars are created,
+    /// and subdirectories are only organised down to the maximum depth.
+    fn organise_dir(
+        &self,
+        root: &Path,
+        path: &Path,
+        depth: usize,
+        pb: ProgressBar,
+        run: &mut Run,
+    ) -> anyhow::Result<bool> {
         log::debug!("organising dir: {}", path.display());
 
         pb.set_message(path.display().to_string());
 
-        let directory = path
-            .read_dir()
-            .with_context(|| format!("failed to read directory: {}", path.display()))?;
+        let directory = match path.read_dir() {
+            Ok(directory) => directory,
+            Err(err) => {
+                report_outcome(run, path, None, Outcome::Failed, Some(err.to_string()));
+                return Err(err)
+                    .with_context(|| format!("failed to read directory: {}", path.display()));
+            }
+        };
 
         log::trace!("read dir: {}", path.display());
 
+        let mut filtered = 0;
+        let mut kept = 0;
+        let mut junk = vec![];
+
         for entry in directory {
-            pb.inc_length(1);
+            if self.interrupted(run) {
+                break;
+            }
 
             log::trace!("organising directory entry");
             if let Err(err) = entry {
@@ -113,42 +424,90 @@ impl Organiser {
                         pb.abandon();
                         return Err(err).context(message);
                     }
-                    false => continue,
+                    false => {
+                        kept += 1;
+                        continue;
+                    }
                 }
             }
 
             let path = &entry.unwrap().path();
 
+            if temp::is_temp(path) {
+                log::trace!("skipping temporary file: {}", path.display());
+                kept += 1;
+                continue;
+            }
+
+            if self.is_state(path) {
+                log::trace!("skipping state file: {}", path.display());
+                kept += 1;
+                continue;
+            }
+
             let result = path.metadata();
 
             if let Err(err) = result {
                 let message = format!("failed to get metadata for: {}", path.display());
                 log::error!("{message}: {err:#}");
+                report_outcome(run, path, None, Outcome::Failed, Some(err.to_string()));
                 match self.settings.exit {
                     true => {
                         pb.abandon();
                         return Err(err).context(message);
                     }
-                    false => continue,
+                    false => {
+                        kept += 1;
+                        continue;
+                    }
                 }
             }
 
             log::trace!("got metadata for: {}", path.display());
             let meta = result.unwrap();
 
+            if self.is_junk(path, &meta) {
+                log::trace!("skipping junk file: {}", path.display());
+                junk.push(path.clone());
+                continue;
+            }
+
+            let relative = path.strip_prefix(root).unwrap_or(path);
+
+            if !self.settings.filter.matches(relative, meta.is_dir()) {
+                log::debug!("skipping filtered path: {}", path.display());
+                report_outcome(run, path, None, Outcome::Skipped, Some("filtered".into()));
+                filtered += 1;
+                kept += 1;
+                continue;
+            }
+
+            let recurse =
+                self.settings.recursive && self.settings.max_depth.is_none_or(|max| depth < max);
+
+            if meta.is_dir() && !recurse {
+                log::trace!("skipping subdirectory: {}", path.display());
+                kept += 1;
+                continue;
+            }
+
+            pb.inc_length(1);
+
             let cpb = self.multibar.insert_after(&pb, ProgressBar::new(1));
             cpb.set_style(pb.style());
             cpb.set_prefix(format!("  {}", pb.prefix()));
 
-            let result = match [meta.is_dir(), self.settings.recursive] {
-                [true, true] => self.organise_dir(path, cpb),
-                [true, false] => {
-                    log::trace!("skipping subdirectory: {}", path.display());
-                    continue;
-                }
-                [false, _] => self.organise_file(path, cpb),
+            let result = match meta.is_dir() {
+                true => self.organise_dir(root, path, depth + 1, cpb, run),
+                false => self
+                    .organise_file(path, root, cpb, run)
+                    .map(|outcome| self.removes_source(outcome)),
             };
 
+            if let Ok(false) = result {
+                kept += 1;
+            }
+
             if let Err(err) = result {
                 let message = format!("failed to organise directory entry: {}", path.display());
                 log::error!("{message}: {err:#}");
@@ -157,7 +516,10 @@ impl Organiser {
                         pb.abandon();
                         return Err(err).context(message);
                     }
-                    false => continue,
+                    false => {
+                        kept += 1;
+                        continue;
+                    }
                 }
             }
 
@@ -166,15 +528,136 @@ impl Organiser {
 
         pb.finish();
 
+        if filtered > 0 {
+            log::info!("skipped {filtered} filtered entries in: {}", path.display());
+        }
+
         log::debug!("organised dir: {}", path.display());
 
-        Ok(())
+        // Top-level input directories are never removed.
+        let prune = self.settings.prune_empty
+            && self.settings.transfer.removes_source()
+            && depth > 0
+            && kept == 0
+            && !self.stop.load(Ordering::Relaxed);
+
+        match prune {
+            true => self.prune_dir(path, junk, run),
+            false => Ok(false),
+        }
+    }
+
+    /// Parse the metadata for the provided file
+    ///
+    /// The detected format is used to decide whether the archive can hold comic info,
+    /// falling back to the format of the file extension.
+    fn parse_meta(
+        &self,
+        path: &Path,
+        name: &str,
+        detected: Option<Format>,
+    ) -> anyhow::Result<Meta> {
+        let parsed = name.parse::<Meta>();
+
+        if !self.settings.read_info {
+            return Ok(parsed?);
+        }
+
+        let format = detected.or_else(|| {
+            path.extension()
+                .and_then(|extension| extension.to_str())
+                .and_then(|extension| extension.parse::<Format>().ok())
+        });
+
+        if format != Some(Format::Cbz) {
+            log::trace!("skipping comic info for non-cbz file: {}", path.display());
+            return Ok(parsed?);
+        }
+
+        let info = match ComicInfo::read(path) {
+            Ok(info) => info,
+            Err(err) => {
+                let err = anyhow::Error::from(err);
+                log::warn!("failed to read comic info: {}: {err:#}", path.display());
+                None
+            }
+        };
+
+        match (parsed, info) {
+            (Ok(mut meta), Some(info)) => {
+                log::trace!("merging comic info: {}", path.display());
+                info.apply(&mut meta, self.settings.prefer);
+                Ok(meta)
+            }
+            (Ok(meta), None) => Ok(meta),
+            (Err(err), Some(info)) => match info.into_meta(Format::Cbz) {
+                Some(meta) => {
+                    log::debug!("using comic info for unparsed name: {}", name);
+                    Ok(meta)
+                }
+                None => Err(err.into()),
+            },
+            (Err(err), None) => Err(err.into()),
+        }
     }
 
-    /// Organise the provided file
-    fn organise_file(&self, path: &Path, pb: ProgressBar) -> anyhow::Result<()> {
-        log::debug!("organising file: {}", path.display());
+    /// Organise the provided file, adding its outcome to the report
+    ///
+    /// The file is planned first, then each step is applied, or only logged and
+    /// collected on a dry run or when making a plan.
+    fn organise_file(
+        &self,
+        path: &Path,
+        root: &Path,
+        pb: ProgressBar,
+        run: &mut Run,
+    ) -> anyhow::Result<Outcome> {
+        let steps = match self.plan_file(path, root, &pb) {
+            Ok(steps) => steps,
+            Err(err) => {
+                let outcome = match err.downcast_ref::<MetaError>() {
+                    Some(_) => Outcome::Unparseable,
+                    None => Outcome::Failed,
+                };
+                if let Mode::Collect(plan) = &mut run.mode {
+                    plan.push(Step::Skip {
+                        source: path.to_path_buf(),
+                        destination: None,
+                        outcome,
+                        reason: format!("{err:#}"),
+                    });
+                }
+                report_outcome(run, path, None, outcome, Some(format!("{err:#}")));
+                return Err(err);
+            }
+        };
+
+        let mut outcome = Outcome::Skipped;
+
+        for step in steps {
+            match self.run_step(step, &pb, run) {
+                Ok(Some(record)) => {
+                    outcome = record.outcome;
+                    run.report.add(record);
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    report_outcome(run, path, None, Outcome::Failed, Some(format!("{err:#}")));
+                    return Err(err);
+                }
+            }
+        }
+
+        Ok(outcome)
+    }
 
+    /// Find the metadata for the provided file, then plan how to organise it
+    ///
+    /// Unchanged files are skipped using the index, and remembered answers for the path
+    /// relative to the root are used before parsing the name. When interactive, the planned
+    /// name is reviewed before it is returned, along with any name that could not be parsed,
+    /// and the answers are remembered.
+    fn plan_file(&self, path: &Path, root: &Path, pb: &ProgressBar) -> anyhow::Result<Vec<Step>> {
         let name = path
             .file_name()
             .context("getting file name")?
@@ -185,140 +668,1061 @@ impl Organiser {
 
         pb.set_message(name.to_string());
 
-        let comic: Meta = name.parse()?;
-        let new_name = comic.to_string();
+        let stamp = Stamp::of(path).context("getting source stamp")?;
+
+        if let Some(destination) = self.unchanged(path, &stamp)? {
+            log::debug!("skipping unchanged file: {}", path.display());
+            return Ok(vec![Step::Skip {
+                source: path.to_path_buf(),
+                destination: Some(destination),
+                outcome: Outcome::Skipped,
+                reason: "unchanged since last run".into(),
+            }]);
+        }
+
+        let detected = match Format::detect(path) {
+            Ok(detected) => detected,
+            Err(err) => {
+                log::warn!("failed to detect format: {}: {err:#}", path.display());
+                None
+            }
+        };
+
+        log::trace!("detected format: {:?}", detected);
+
+        let key = Overrides::key(root, path);
+        let rule = self.override_rule(&key, &stamp)?;
+        let mut answered = false;
+
+        let comic = match &rule {
+            Some(Rule::Skip) => {
+                log::debug!("skipping file by override: {}", path.display());
+                return Ok(vec![Step::Skip {
+                    source: path.to_path_buf(),
+                    destination: None,
+                    outcome: Outcome::Skipped,
+                    reason: "skipped by override".into(),
+                }]);
+            }
+            Some(Rule::Use { meta }) => {
+                log::debug!("using override metadata: {}", path.display());
+                meta.as_ref().clone()
+            }
+            Some(Rule::Accept) | None => match self.parse_meta(path, name, detected) {
+                Ok(meta) => meta,
+                Err(err) if self.settings.interactive && rule.is_none() => {
+                    let format = detected.unwrap_or(Format::Cbz);
+                    let answer = self.ask_unparsed(path, name, format, &err)?;
+                    self.remember(&key, &stamp, &answer)?;
+
+                    match answer {
+                        Some(Rule::Use { meta }) => {
+                            answered = true;
+                            *meta
+                        }
+                        Some(Rule::Accept | Rule::Skip) | None => {
+                            return Ok(vec![Step::Skip {
+                                source: path.to_path_buf(),
+                                destination: None,
+                                outcome: Outcome::Skipped,
+                                reason: "skipped when asked".into(),
+                            }]);
+                        }
+                    }
+                }
+                Err(err) => return Err(err),
+            },
+        };
+
+        let planner = self.planner();
+        let steps = planner.plan_file(path, comic, detected)?;
 
-        log::trace!("new name: {}", &new_name);
+        if !self.settings.interactive || rule.is_some() || answered {
+            return Ok(steps);
+        }
 
-        let output_dir = &self.settings.output;
+        let Some(
+            Step::Transfer {
+                destination, meta, ..
+            }
+            | Step::Convert {
+                destination, meta, ..
+            },
+        ) = steps.last()
+        else {
+            return Ok(steps);
+        };
 
-        if output_dir.exists() && !output_dir.is_dir() {
-            log::error!("output path is not a directory: {}", output_dir.display());
-            anyhow::bail!("output path is not a directory");
+        let answer = self.ask_review(name, destination, meta)?;
+        self.remember(&key, &stamp, &answer)?;
+
+        match answer {
+            Some(Rule::Accept) => Ok(steps),
+            Some(Rule::Use { meta }) => planner.plan_file(path, *meta, detected),
+            Some(Rule::Skip) | None => Ok(vec![Step::Skip {
+                source: path.to_path_buf(),
+                destination: Some(destination.clone()),
+                outcome: Outcome::Skipped,
+                reason: "skipped when asked".into(),
+            }]),
         }
+    }
 
-        let output_dir = match self.settings.series {
-            true => output_dir.join(comic.series),
-            false => output_dir.to_path_buf(),
+    /// Run the provided step, returning the record of the file it organises
+    ///
+    /// Collected steps are only logged and added to the plan. Directories already in the
+    /// plan are left out, as they would be created by an earlier step.
+    fn run_step(
+        &self,
+        step: Step,
+        pb: &ProgressBar,
+        run: &mut Run,
+    ) -> anyhow::Result<Option<Record>> {
+        let plan = match &mut run.mode {
+            Mode::Apply => return self.apply_step(step, pb),
+            Mode::Collect(plan) => plan,
         };
 
-        log::trace!("output dir: {}", output_dir.display());
+        let message = format!("would {step}");
+        let record = step.record();
+
+        if plan.push(step) {
+            log::info!("{message}");
+        }
+
+        if let Some(record) = &record {
+            self.finish_progress(pb, record);
+        }
+
+        Ok(record)
+    }
 
-        match [output_dir.exists(), self.settings.dry_run] {
-            [false, false] => {
-                log::info!("creating output dir: {}", output_dir.display());
-                fs::create_dir_all(&output_dir).context("creating output dir")?;
+    /// Apply the provided step, returning the record of the file it organises
+    fn apply_step(&self, step: Step, pb: &ProgressBar) -> anyhow::Result<Option<Record>> {
+        match step {
+            Step::CreateDir { path, .. } => {
+                match path.exists() {
+                    true => log::trace!("dir exists: {}", path.display()),
+                    false => {
+                        log::info!("creating dir: {}", path.display());
+                        self.create_dirs(&path).context("creating dir")?;
+                    }
+                }
+                Ok(None)
+            }
+            Step::Transfer { .. } | Step::Convert { .. } => self.place_file(step, pb).map(Some),
+            Step::Skip { .. } => {
+                let record = step.record();
+                if let Some(record) = &record {
+                    self.finish_progress(pb, record);
+                }
+                Ok(record)
+            }
+            Step::RemoveDir { path, junk, .. } => {
+                self.remove_dir(&path, &junk)?;
+                Ok(None)
             }
-            [false, true] => log::info!("would create output dir: {}", output_dir.display()),
-            _ => log::trace!("output dir exists: {}", output_dir.display()),
         }
+    }
 
-        let new_path = output_dir.join(new_name.clone());
+    /// Place a file by applying the provided transfer or convert step, returning its record
+    fn place_file(&self, step: Step, pb: &ProgressBar) -> anyhow::Result<Record> {
+        let convert = match &step {
+            Step::Convert {
+                format,
+                remove_source,
+                ..
+            } => Some((*format, *remove_source)),
+            _ => None,
+        };
+
+        let mut transfer = match &step {
+            Step::Transfer { transfer, .. } => *transfer,
+            _ => self.settings.transfer,
+        };
+
+        // Hardlinks and reflinks can fall back to copying, but the index keeps the requested mode.
+        let requested = transfer;
+
+        let (source, mut destination, overwrite, write_info, meta) = match step {
+            Step::Transfer {
+                source,
+                destination,
+                overwrite,
+                write_info,
+                meta,
+                ..
+            }
+            | Step::Convert {
+                source,
+                destination,
+                overwrite,
+                write_info,
+                meta,
+                ..
+            } => (source, destination, overwrite, write_info, meta),
+            _ => anyhow::bail!("step does not place a file"),
+        };
 
-        log::trace!("new path: {}", new_path.display());
+        let stamp = Stamp::of(&source).context("getting source stamp")?;
+        let mut overwritten = false;
+
+        // The output may have changed since the plan was made, so conflicts are resolved again.
+        if destination.exists() && !transfer::is_case_rename(&source, &destination) {
+            let resolution = match overwrite {
+                true => Resolution::Overwrite,
+                false => self.resolve_conflict(&source, &destination)?,
+            };
 
-        if new_path.exists() {
-            log::debug!("file already exists: {}", new_path.display());
-            match [self.settings.dry_run, self.settings.force] {
-                [true, true] => {
-                    log::warn!("would overwrite existing file: {}", new_path.display());
+            match resolution {
+                Resolution::Overwrite => {
+                    self.prepare_overwrite(&destination, convert.is_some(), transfer)?;
+                    overwritten = true;
                 }
-                [true, false] => {
-                    log::warn!("would skip existing file: {}", new_path.display());
-                    pb.finish_with_message(format!("{}: would skip", pb.message()));
-                    return Ok(());
+                Resolution::Rename(renamed) => {
+                    if let Some(parent) = renamed.parent().filter(|parent| !parent.exists()) {
+                        self.create_dirs(parent).context("creating conflicts dir")?;
+                    }
+                    destination = renamed;
                 }
-                [false, true] => {
-                    log::warn!("overwriting existing file: {}", new_path.display());
+                Resolution::Displace(moved) => {
+                    if let Some(parent) = moved.parent().filter(|parent| !parent.exists()) {
+                        self.create_dirs(parent).context("creating conflicts dir")?;
+                    }
+                    self.displace(&destination, &moved)
+                        .context("moving existing file")?;
                 }
-                [false, false] => {
-                    log::warn!("skipping existing file: {}", new_path.display());
-                    pb.finish_with_message(format!("{}: skipped", pb.message()));
-                    return Ok(());
+                Resolution::Skip => {
+                    let record = Record {
+                        source,
+                        destination: Some(destination),
+                        outcome: Outcome::Skipped,
+                        reason: Some(format!("file already exists ({})", self.settings.conflict)),
+                    };
+                    self.finish_progress(pb, &record);
+                    return Ok(record);
                 }
             }
         }
 
-        match [self.settings.dry_run, self.settings.move_files] {
-            [true, true] => {
-                log::info!("would move: {} -> {}", path.display(), new_path.display());
-            }
-            [true, false] => {
-                log::info!("would copy: {} -> {}", path.display(), new_path.display());
-            }
-            [false, true] => {
-                log::info!("moving: {} -> {}", path.display(), new_path.display());
-                if let Err(err) = fs::rename(path, new_path).context("moving file") {
-                    log::error!("failed to move file: {}", err);
+        if let Some(parent) = destination.parent() {
+            self.clean_temp(parent);
+        }
+
+        match convert {
+            Some((format, remove_source)) => {
+                log::info!(
+                    "converting: {} -> {}",
+                    source.display(),
+                    destination.display()
+                );
+                let result = self.convert_file(&source, format, &destination, remove_source);
+                if let Err(err) = result.context("converting file") {
+                    log::error!("failed to convert file: {}", err);
                     pb.abandon_with_message(format!("{}: {}", pb.message(), err));
                     return Err(err);
                 }
             }
-            [false, false] => {
-                log::info!("copying: {} -> {}", path.display(), new_path.display());
-                if let Err(err) = fs::copy(path, new_path).context("copying file") {
-                    log::error!("failed to copy file: {}", err);
-                    pb.abandon_with_message(format!("{}: {}", pb.message(), err));
-                    return Err(err);
+            None => {
+                log::info!(
+                    "transferring ({transfer}): {} -> {}",
+                    source.display(),
+                    destination.display()
+                );
+                match self
+                    .transfer_file(&source, &destination, transfer)
+                    .context("transferring file")
+                {
+                    Ok(used) => transfer = used,
+                    Err(err) => {
+                        log::error!("failed to transfer file: {}", err);
+                        pb.abandon_with_message(format!("{}: {}", pb.message(), err));
+                        return Err(err);
+                    }
                 }
             }
         }
 
-        if log::max_level() >= log::LevelFilter::Info {
-            pb.finish_with_message(format!("{} -> {}", name, new_name));
-        } else {
-            pb.finish_and_clear();
+        // The file is already in place, so failing to write comic info does not fail it.
+        let mut reason = None;
+
+        if write_info {
+            if let Err(err) = self.write_info(&destination, &meta) {
+                log::warn!(
+                    "failed to write comic info: {}: {err:#}",
+                    destination.display()
+                );
+                reason = Some(format!("comic info not written: {err:#}"));
+            }
         }
 
-        log::debug!("organised file: {}", path.display());
+        log::debug!("organised file: {}", source.display());
 
-        Ok(())
-    }
-}
+        let outcome = match (overwritten, convert, transfer) {
+            (true, _, _) => Outcome::Overwritten,
+            (false, Some(_), _) => Outcome::Converted,
+            (false, None, Transfer::Move) => Outcome::Moved,
+            (false, None, Transfer::Hardlink | Transfer::Symlink | Transfer::RelativeSymlink) => {
+                Outcome::Linked
+            }
+            (false, None, Transfer::Copy | Transfer::Reflink) => Outcome::Copied,
+        };
 
-#[cfg(test)]
-pub(crate) mod tests {
-    use temp_dir::TempDir;
-    use test_log::test;
+        if let Err(err) = self.index_file(&source, &destination, stamp, meta, requested) {
+            log::warn!("failed to index file: {}: {err:#}", destination.display());
+        }
 
-    use super::*;
+        let record = Record {
+            source,
+            destination: Some(destination),
+            outcome,
+            reason,
+        };
 
-    #[test]
-    fn test_organise_multiple_paths() {
-        let dir = TempDir::new().expect("should create temp dir");
-        let source_dir = dir.child("source");
-        let output_dir = dir.child("output");
-        let source_sub_dir = source_dir.join("sub");
+        self.finish_progress(pb, &record);
 
-        let name_1 = "Test 001.cbz";
-        let source_file_1 = source_dir.join(name_1);
-        let output_file_1 = output_dir.join(name_1);
+        Ok(record)
+    }
 
-        let name_2 = "Test 002.cbz";
-        let source_file_2 = source_sub_dir.join(name_2);
-        let output_file_2 = output_dir.join(name_2);
+    /// Write the metadata to ComicInfo.xml in the organised file, keeping its attributes
+    fn write_info(&self, path: &Path, meta: &Meta) -> anyhow::Result<()> {
+        log::info!("writing comic info: {}", path.display());
+
+        let backup = self.snapshot(path)?;
+        let preserve = &self.settings.preserve;
+        let attributes = preserve
+            .capture(path)
+            .context("reading organised file attributes");
+
+        let result = attributes.and_then(|attributes| {
+            ComicInfo::write(path, meta).context("writing comic info")?;
+            Ok(attributes)
+        });
+
+        // The file was not changed, so the snapshot would never be recorded.
+        let attributes = match result {
+            Ok(attributes) => attributes,
+            Err(err) => {
+                if let Some(backup) = &backup {
+                    let _ = fs::remove_file(backup);
+                }
+                return Err(err);
+            }
+        };
+        preserve
+            .apply_file(&attributes, path)
+            .context("restoring organised file attributes")?;
+
+        if let Some(backup) = backup {
+            self.record(Entry::Modify {
+                path: absolute(path),
+                backup,
+                stamp: Stamp::of(path).context("getting modified file stamp")?,
+            })?;
+        }
 
-        std::fs::create_dir_all(&source_dir).expect("should create source dir");
-        std::fs::create_dir_all(&output_dir).expect("should create output dir");
-        std::fs::create_dir_all(&source_sub_dir).expect("should create source sub dir");
-        fs::write(&source_file_1, "").expect("should create first source file");
-        fs::write(&source_file_2, "").expect("should create second source file");
+        Ok(())
+    }
 
-        let settings = Settings {
-            output: output_dir,
-            ..Default::default()
-        };
+    /// Finish the progress bar for a file with the provided record
+    fn finish_progress(&self, pb: &ProgressBar, record: &Record) {
+        match (record.outcome, &record.reason, &record.destination) {
+            (Outcome::Skipped, Some(reason), _) => {
+                pb.finish_with_message(format!("{}: {reason}", pb.message()));
+            }
+            (_, _, Some(destination)) if log::max_level() >= log::LevelFilter::Info => {
+                let new_name = destination
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy();
+                pb.finish_with_message(format!("{} -> {}", pb.message(), new_name));
+            }
+            _ => pb.finish_and_clear(),
+        }
+    }
+}
 
-        let organiser = Organiser::new(settings, Default::default());
+impl Organiser {
+    /// Resolve a conflict with an existing file at the new path using the conflict policy
+    fn resolve_conflict(&self, path: &Path, new_path: &Path) -> anyhow::Result<Resolution> {
+        log::debug!("file already exists: {}", new_path.display());
 
-        let paths = vec![source_file_1.clone(), source_sub_dir.clone()];
+        let policy = self.settings.conflict;
+        let conflicts = self.planner().output_root(path).join(Conflict::DIR);
 
-        organiser.organise(paths).expect("should organise");
+        let resolution = policy
+            .resolve(path, new_path, &conflicts)
+            .context("resolving conflict")?;
 
-        assert!(
-            source_file_1.exists(),
-            "source file should still exist: {}",
-            source_file_1.display()
+        match &resolution {
+            Resolution::Skip => {
+                log::warn!("skipping existing file ({policy}): {}", new_path.display());
+            }
+            Resolution::Overwrite => {}
+            Resolution::Rename(renamed) => {
+                log::warn!(
+                    "keeping existing file ({policy}): {} -> {}",
+                    new_path.display(),
+                    renamed.display()
+                );
+            }
+            Resolution::Displace(moved) => {
+                log::warn!(
+                    "moving existing file ({policy}): {} -> {}",
+                    new_path.display(),
+                    moved.display()
+                );
+            }
+        }
+
+        Ok(resolution)
+    }
+
+    /// Move the existing file at the new path out of the way, recording it in the journal
+    fn displace(&self, new_path: &Path, moved: &Path) -> anyhow::Result<()> {
+        transfer::move_file(new_path, moved)?;
+
+        self.record(Entry::Move {
+            source: absolute(new_path),
+            destination: absolute(moved),
+            stamp: Stamp::of(moved).context("getting moved file stamp")?,
+        })
+    }
+
+    /// Back up or remove the existing file at the new path before it is overwritten
+    fn prepare_overwrite(
+        &self,
+        new_path: &Path,
+        convert: bool,
+        transfer: Transfer,
+    ) -> anyhow::Result<()> {
+        log::warn!(
+            "overwriting existing file ({}): {}",
+            self.settings.conflict,
+            new_path.display()
+        );
+
+        match self.backup(new_path)? {
+            Some(backup) => self.record(Entry::Overwrite {
+                path: absolute(new_path),
+                backup,
+            })?,
+            None if !convert && !transfer.replaces_existing() => {
+                fs::remove_file(new_path).context("removing existing file")?;
+            }
+            None => {}
+        }
+
+        Ok(())
+    }
+
+    /// Transfer the provided file to the new path, returning the transfer that was used
+    ///
+    /// The transfer is recorded as soon as it is made, so it can be undone even if
+    /// the source attributes then fail to apply, which only warns.
+    fn transfer_file(
+        &self,
+        path: &Path,
+        new_path: &Path,
+        transfer: Transfer,
+    ) -> anyhow::Result<Transfer> {
+        let preserve = &self.settings.preserve;
+        let attributes = preserve
+            .capture(path)
+            .context("reading source attributes")?;
+
+        let transfer = transfer.apply(path, new_path)?;
+
+        let preserved = matches!(
+            transfer,
+            Transfer::Copy | Transfer::Move | Transfer::Reflink
+        );
+
+        let source = absolute(path);
+        let destination = absolute(new_path);
+
+        let entry = match transfer {
+            Transfer::Move => Entry::Move {
+                source,
+                destination,
+                stamp: preserve
+                    .stamp(&attributes, new_path)
+                    .context("getting moved file stamp")?,
+            },
+            Transfer::Symlink | Transfer::RelativeSymlink => Entry::Link {
+                source,
+                destination,
+            },
+            Transfer::Copy | Transfer::Hardlink | Transfer::Reflink => Entry::Copy {
+                source,
+                destination,
+                stamp: match preserved {
+                    true => preserve.stamp(&attributes, new_path),
+                    false => Stamp::of(new_path),
+                }
+                .context("getting copied file stamp")?,
+            },
+        };
+
+        self.record(entry)?;
+
+        if preserved {
+            if let Err(err) = preserve.apply_file(&attributes, new_path) {
+                log::warn!(
+                    "failed to apply source attributes: {}: {err}",
+                    new_path.display()
+                );
+
+                // Keep the recorded times, so the file can still be undone
+                if let Err(err) = preserve.apply_times(&attributes, new_path) {
+                    log::warn!("failed to keep times: {}: {err}", new_path.display());
+                }
+            }
+        }
+
+        Ok(transfer)
+    }
+
+    /// Convert the provided file to a CBZ at the new path
+    ///
+    /// The archive is written to a temporary file and renamed into place,
+    /// then the source is removed if requested.
+    /// Converted files cannot be linked, so other transfers keep the source.
+    fn convert_file(
+        &self,
+        path: &Path,
+        format: Format,
+        new_path: &Path,
+        remove_source: bool,
+    ) -> anyhow::Result<()> {
+        let preserve = &self.settings.preserve;
+        let attributes = preserve
+            .capture(path)
+            .context("reading source attributes")?;
+
+        let temp = temp::path(new_path);
+
+        let result = convert_to_cbz(path, format, &temp)
+            .map_err(anyhow::Error::from)
+            .and_then(|_| {
+                let result = preserve.apply_file(&attributes, &temp);
+                result.context("applying source attributes")
+            });
+
+        if let Err(err) = result {
+            let _ = fs::remove_file(&temp);
+            return Err(err);
+        }
+
+        fs::rename(&temp, new_path).context("renaming converted file")?;
+
+        self.record(Entry::Convert {
+            source: absolute(path),
+            destination: absolute(new_path),
+            stamp: Stamp::of(new_path).context("getting converted file stamp")?,
+        })?;
+
+        if !remove_source {
+            return Ok(());
+        }
+
+        match self.backup(path)? {
+            Some(backup) => {
+                log::debug!("backed up converted source: {}", path.display());
+                self.record(Entry::Remove {
+                    path: absolute(path),
+                    backup,
+                })?;
+            }
+            None => {
+                log::debug!("removing converted source: {}", path.display());
+                fs::remove_file(path).context("removing converted source")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Organiser {
+    /// Run the provided function with the journal for this run
+    ///
+    /// The journal is created in the output directory on first use.
+    /// Returns None without calling the function if journaling is disabled or on a dry run.
+    fn with_journal<T>(
+        &self,
+        f: impl FnOnce(&mut Journal) -> io::Result<T>,
+    ) -> anyhow::Result<Option<T>> {
+        if !self.settings.journal || self.settings.dry_run {
+            return Ok(None);
+        }
+
+        let mut journal = self.journal.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if journal.is_none() {
+            let created = Journal::create(&self.settings.output).context("creating journal")?;
+            *journal = Some(created);
+        }
+
+        let journal = journal.as_mut().expect("journal should have been created");
+
+        Ok(Some(f(journal).context("updating journal")?))
+    }
+
+    /// Run the provided function with the index of organised files
+    ///
+    /// The index is loaded from the output directory on first use.
+    /// Returns None without calling the function if the index is disabled.
+    fn with_index<T>(&self, f: impl FnOnce(&mut Index) -> T) -> anyhow::Result<Option<T>> {
+        if !self.settings.index {
+            return Ok(None);
+        }
+
+        let mut index = self.index.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if index.is_none() {
+            let loaded = Index::load(&self.settings.output).context("loading index")?;
+            *index = Some(loaded);
+        }
+
+        let index = index.as_mut().expect("index should have been loaded");
+
+        Ok(Some(f(index)))
+    }
+
+    /// Save the index to the output directory, if it has been loaded and changed
+    fn save_index(&self) -> anyhow::Result<()> {
+        if self.settings.dry_run {
+            return Ok(());
+        }
+
+        let mut index = self.index.lock().unwrap_or_else(PoisonError::into_inner);
+
+        match index.as_mut() {
+            Some(index) => index.save(&self.settings.output).context("saving index"),
+            None => Ok(()),
+        }
+    }
+
+    /// Run the provided function with the override rules for organising files
+    ///
+    /// The overrides are loaded from the output directory on first use.
+    fn with_overrides<T>(&self, f: impl FnOnce(&mut Overrides) -> T) -> anyhow::Result<T> {
+        let mut overrides = self
+            .overrides
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        if overrides.is_none() {
+            let loaded = match self.settings.remember {
+                true => Overrides::load(&self.settings.output).context("loading overrides")?,
+                false => Overrides::default(),
+            };
+            *overrides = Some(loaded);
+        }
+
+        let overrides = overrides
+            .as_mut()
+            .expect("overrides should have been loaded");
+
+        Ok(f(overrides))
+    }
+
+    /// Save the override rules to the output directory, if they have been loaded and changed
+    ///
+    /// Answers are still remembered on a dry run, as they do not change any files.
+    fn save_overrides(&self) -> anyhow::Result<()> {
+        if !self.settings.remember {
+            return Ok(());
+        }
+
+        let mut overrides = self
+            .overrides
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        match overrides.as_mut() {
+            Some(overrides) => overrides
+                .save(&self.settings.output)
+                .context("saving overrides"),
+            None => Ok(()),
+        }
+    }
+
+    /// Get the override rule for the file with the provided key and stamp
+    ///
+    /// Rules are only used when interactive, or when opted in outside interactive mode.
+    fn override_rule(&self, key: &str, stamp: &Stamp) -> anyhow::Result<Option<Rule>> {
+        if !self.settings.interactive && !self.settings.use_overrides {
+            return Ok(None);
+        }
+
+        self.with_overrides(|overrides| overrides.get(key, stamp).cloned())
+    }
+
+    /// Remember the provided answer for the file with the provided key and stamp
+    fn remember(&self, key: &str, stamp: &Stamp, answer: &Option<Rule>) -> anyhow::Result<()> {
+        if let Some(rule) = answer {
+            self.with_overrides(|overrides| overrides.insert(key, stamp.clone(), rule.clone()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Ask how to organise a file whose name could not be parsed
+    ///
+    /// Returns None if the question was dismissed.
+    fn ask_unparsed(
+        &self,
+        path: &Path,
+        name: &str,
+        format: Format,
+        err: &anyhow::Error,
+    ) -> anyhow::Result<Option<Rule>> {
+        let suggestions = prompt::suggestions(path)
+            .into_iter()
+            .map(|meta| {
+                let (_, new_path) = self.planner().destination(Path::new(""), &meta);
+                (new_path.display().to_string(), meta)
+            })
+            .collect();
+
+        let error = format!("{err:#}");
+
+        self.multibar
+            .suspend(|| prompt::resolve(name, &error, format, suggestions))
+            .context("asking how to organise file")
+    }
+
+    /// Ask whether to organise a file as proposed
+    ///
+    /// Returns the rule to organise the file with, or None if the question was dismissed.
+    fn ask_review(&self, name: &str, new_path: &Path, meta: &Meta) -> anyhow::Result<Option<Rule>> {
+        self.multibar
+            .suspend(|| prompt::review(name, new_path, meta))
+            .context("asking to confirm rename")
+    }
+
+    /// Get the destination of the provided file if it is unchanged since it was last organised
+    fn unchanged(&self, path: &Path, stamp: &Stamp) -> anyhow::Result<Option<PathBuf>> {
+        if self.settings.rescan {
+            return Ok(None);
+        }
+
+        let destination = self.with_index(|index| {
+            let item = index.unchanged(&absolute(path), stamp, self.settings.transfer);
+            item.map(|item| item.destination.clone())
+        })?;
+
+        Ok(destination.flatten())
+    }
+
+    /// Add an organised file to the index
+    fn index_file(
+        &self,
+        source: &Path,
+        destination: &Path,
+        source_stamp: Stamp,
+        meta: Meta,
+        transfer: Transfer,
+    ) -> anyhow::Result<()> {
+        if !self.settings.index {
+            return Ok(());
+        }
+
+        let stamp = Stamp::of(destination).context("getting organised file stamp")?;
+        let hash = transfer::checksum(destination).context("hashing organised file")?;
+
+        let item = Item {
+            source: absolute(source),
+            source_stamp,
+            destination: absolute(destination),
+            stamp,
+            hash: hash.iter().map(|byte| format!("{byte:02x}")).collect(),
+            meta,
+            transfer,
+        };
+
+        self.with_index(|index| index.insert(item))?;
+
+        Ok(())
+    }
+
+    /// Whether the source of a file with the provided outcome has been removed
+    fn removes_source(&self, outcome: Outcome) -> bool {
+        self.settings.transfer.removes_source()
+            && matches!(
+                outcome,
+                Outcome::Moved | Outcome::Converted | Outcome::Overwritten
+            )
+    }
+
+    /// Whether the provided path is the journal, index or overrides kept in the output directory
+    fn is_state(&self, path: &Path) -> bool {
+        let path = absolute(path);
+        let output = absolute(&self.settings.output);
+
+        path.starts_with(output.join(Journal::DIR))
+            || path == output.join(Index::PATH)
+            || path == output.join(Overrides::PATH)
+    }
+
+    /// Whether the provided file is junk that does not stop its directory being pruned
+    fn is_junk(&self, path: &Path, meta: &fs::Metadata) -> bool {
+        let Some(name) = path.file_name() else {
+            return false;
+        };
+
+        self.settings.prune_empty
+            && meta.is_file()
+            && self.settings.junk.iter().any(|glob| glob.is_match(name))
+    }
+
+    /// Remove a source directory left empty after moving, along with any junk files in it
+    ///
+    /// Returns whether the directory was, or would be, removed.
+    fn prune_dir(&self, path: &Path, junk: Vec<PathBuf>, run: &mut Run) -> anyhow::Result<bool> {
+        let plan = match &mut run.mode {
+            Mode::Apply => return self.remove_dir(path, &junk),
+            Mode::Collect(plan) => plan,
+        };
+
+        let step = Step::RemoveDir {
+            path: path.to_path_buf(),
+            junk,
+            reason: "left empty after moving".into(),
+        };
+
+        log::info!("would {step}");
+        plan.push(step);
+
+        Ok(true)
+    }
+
+    /// Remove the provided directory if it only holds the provided junk files
+    ///
+    /// Returns whether the directory was removed.
+    fn remove_dir(&self, path: &Path, junk: &[PathBuf]) -> anyhow::Result<bool> {
+        let mut entries = path.read_dir().context("reading dir to prune")?;
+
+        if entries.any(|entry| entry.map_or(true, |entry| !junk.contains(&entry.path()))) {
+            log::debug!("dir is not empty, keeping: {}", path.display());
+            return Ok(false);
+        }
+
+        for file in junk {
+            log::info!("removing junk file: {}", file.display());
+            match self.backup(file)? {
+                Some(backup) => self.record(Entry::Remove {
+                    path: absolute(file),
+                    backup,
+                })?,
+                None => fs::remove_file(file).context("removing junk file")?,
+            }
+        }
+
+        log::info!("removing empty dir: {}", path.display());
+        fs::remove_dir(path).context("removing empty dir")?;
+        self.record(Entry::RemoveDir {
+            path: absolute(path),
+        })?;
+
+        Ok(true)
+    }
+
+    /// Whether organising has been stopped, marking the report as interrupted
+    fn interrupted(&self, run: &mut Run) -> bool {
+        if !self.stop.load(Ordering::Relaxed) {
+            return false;
+        }
+
+        if !run.report.interrupted {
+            log::warn!("interrupted, stopping after the current file");
+            run.report.interrupted = true;
+        }
+
+        true
+    }
+
+    /// Record an entry in the journal for this run
+    fn record(&self, entry: Entry) -> anyhow::Result<()> {
+        self.with_journal(|journal| journal.record(&entry))?;
+        Ok(())
+    }
+
+    /// Move the provided file into the journal backups, returning the backup path
+    ///
+    /// Returns None without moving the file if backups are disabled.
+    fn backup(&self, path: &Path) -> anyhow::Result<Option<PathBuf>> {
+        if !self.settings.backup {
+            return Ok(None);
+        }
+
+        self.with_journal(|journal| {
+            let backup = journal.backup_path(path)?;
+            transfer::move_file(path, &backup)?;
+            Ok(backup)
+        })
+    }
+
+    /// Keep a copy of the provided file in the journal backups before it is changed
+    ///
+    /// The file is hard linked where possible, as changes replace the file rather than
+    /// writing to it. Copies keep the times of the file, so restoring the backup
+    /// leaves the file as it was recorded in the journal.
+    /// Returns None without keeping a copy if backups are disabled.
+    fn snapshot(&self, path: &Path) -> anyhow::Result<Option<PathBuf>> {
+        if !self.settings.backup {
+            return Ok(None);
+        }
+
+        self.with_journal(|journal| {
+            let backup = journal.backup_path(path)?;
+            if fs::hard_link(path, &backup).is_err() {
+                fs::copy(path, &backup)?;
+                let meta = path.metadata()?;
+                filetime::set_file_times(
+                    &backup,
+                    FileTime::from_last_access_time(&meta),
+                    FileTime::from_last_modification_time(&meta),
+                )?;
+            }
+            Ok(backup)
+        })
+    }
+
+    /// Remove leftover temporary files from the provided output directory, once per run
+    fn clean_temp(&self, dir: &Path) {
+        let mut cleaned = self.cleaned.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if !cleaned.insert(dir.to_path_buf()) {
+            return;
+        }
+
+        if let Err(err) = temp::clean(dir) {
+            log::warn!(
+                "failed to remove leftover temporary files: {}: {err:#}",
+                dir.display()
+            );
+        }
+    }
+
+    /// Get a planner for the steps that organise each file
+    fn planner(&self) -> Planner<'_> {
+        Planner::new(&self.settings)
+    }
+
+    /// Create the provided directory and any missing parents
+    ///
+    /// Directories created below the output directory, or below the parent of
+    /// the file when renaming in place, get the configured ownership and mode,
+    /// and are recorded in the journal.
+    fn create_dirs(&self, dir: &Path) -> anyhow::Result<()> {
+        let missing = self.missing_dirs(dir);
+        fs::create_dir_all(dir)?;
+
+        for path in missing {
+            self.settings
+                .preserve
+                .apply_dir(&path)
+                .context("setting dir attributes")?;
+            self.record(Entry::CreateDir { path })?;
+        }
+
+        Ok(())
+    }
+
+    /// Get the missing directories that would be created for the provided directory, top-down
+    ///
+    /// The output directory and its parents are left out, as undo never removes them.
+    fn missing_dirs(&self, dir: &Path) -> Vec<PathBuf> {
+        let output = absolute(&self.settings.output);
+
+        let mut missing: Vec<PathBuf> = dir
+            .ancestors()
+            .take_while(|ancestor| !ancestor.exists())
+            .map(absolute)
+            .filter(|ancestor| !output.starts_with(ancestor))
+            .collect();
+
+        missing.reverse();
+        missing
+    }
+}
+
+/// How long to wait for watch events before checking for a stop signal
+const WATCH_POLL: Duration = Duration::from_millis(500);
+
+/// Add the outcome of a file to the report for the provided run
+fn report_outcome(
+    run: &mut Run,
+    source: &Path,
+    destination: Option<PathBuf>,
+    outcome: Outcome,
+    reason: Option<String>,
+) {
+    run.report.add(Record {
+        source: source.to_path_buf(),
+        destination,
+        outcome,
+        reason,
+    });
+}
+
+/// Get the style used for progress bars
+fn progress_style() -> anyhow::Result<ProgressStyle> {
+    Ok(ProgressStyle::default_bar()
+        .template("{prefix}{msg} [{wide_bar}] [{pos}/{len}] [{duration}]")
+        .context("setting progress bar style")?
+        .progress_chars("=> "))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use temp_dir::TempDir;
+    use test_log::test;
+
+    use globset::Glob;
+
+    use super::*;
+    use crate::organise::{Filter, Preserve};
+
+    #[test]
+    fn test_organise_multiple_paths() {
+        let dir = TempDir::new().expect("should create temp dir");
+        let source_dir = dir.child("source");
+        let output_dir = dir.child("output");
+        let source_sub_dir = source_dir.join("sub");
+
+        let name_1 = "Test 001.cbz";
+        let source_file_1 = source_dir.join(name_1);
+        let output_file_1 = output_dir.join(name_1);
+
+        let name_2 = "Test 002.cbz";
+        let source_file_2 = source_sub_dir.join(name_2);
+        let output_file_2 = output_dir.join(name_2);
+
+        std::fs::create_dir_all(&source_dir).expect("should create source dir");
+        std::fs::create_dir_all(&output_dir).expect("should create output dir");
+        std::fs::create_dir_all(&source_sub_dir).expect("should create source sub dir");
+        fs::write(&source_file_1, "").expect("should create first source file");
+        fs::write(&source_file_2, "").expect("should create second source file");
+
+        let settings = Settings {
+            output: output_dir,
+            ..Default::default()
+        };
+
+        let organiser = Organiser::new(settings, Default::default());
+
+        let paths = vec![source_file_1.clone(), source_sub_dir.clone()];
+
+        organiser.organise(paths).expect("should organise");
+
+        assert!(
+            source_file_1.exists(),
+            "source file should still exist: {}",
+            source_file_1.display()
         );
         assert!(
             output_file_1.exists(),
@@ -448,7 +1852,7 @@ pub(crate) mod tests {
 
         let settings = Settings {
             output: output_dir,
-            move_files: true,
+            transfer: Transfer::Move,
             ..Default::default()
         };
 
@@ -487,7 +1891,7 @@ pub(crate) mod tests {
 
         let settings = Settings {
             output: output_dir.clone(),
-            move_files: true,
+            transfer: Transfer::Move,
             exit: true,
             ..Default::default()
         };
@@ -534,8 +1938,8 @@ pub(crate) mod tests {
 
         let settings = Settings {
             output: output_dir,
-            move_files: true,
-            force: true,
+            transfer: Transfer::Move,
+            conflict: Conflict::Overwrite,
             ..Default::default()
         };
 
@@ -581,8 +1985,8 @@ pub(crate) mod tests {
 
         let settings = Settings {
             output: output_dir,
-            move_files: true,
-            force: false,
+            transfer: Transfer::Move,
+            conflict: Conflict::Skip,
             ..Default::default()
         };
 
@@ -662,113 +2066,1485 @@ pub(crate) mod tests {
     }
 
     #[test]
-    fn test_organise_creates_output_dir() {
+    fn test_organise_series_volume() {
         let dir = TempDir::new().expect("should create temp dir");
         let source_dir = dir.child("source");
         let output_dir = dir.child("output");
 
-        let name = "Test 001.cbz";
-        let source_file = source_dir.join(name);
-        let output_file = output_dir.join(name);
+        let series_dir = output_dir.join("Test");
+        let volume_dir = series_dir.join("v2");
 
-        std::fs::create_dir_all(&source_dir).expect("should create source dir");
-        fs::write(&source_file, "").expect("should create source file");
+        let source_file_1 = source_dir.join("Test Vol. 2 001.cbz");
+        let source_file_2 = source_dir.join("Test Volume 02 002.cbz");
+        let output_file_1 = volume_dir.join("Test v2 001.cbz");
+        let output_file_2 = volume_dir.join("Test v2 002.cbz");
 
-        assert!(
-            !output_dir.exists(),
-            "output dir should not exist before organise: {}",
-            output_dir.display()
-        );
+        std::fs::create_dir_all(&source_dir).expect("should create source dir");
+        fs::write(&source_file_1, "").expect("should create first source file");
+        fs::write(&source_file_2, "").expect("should create second source file");
 
         let settings = Settings {
-            output: output_dir.clone(),
+            output: output_dir,
+            series: true,
             ..Default::default()
         };
 
         let organiser = Organiser::new(settings, Default::default());
 
-        let paths = vec![source_dir.clone()];
+        let paths = vec![source_dir];
 
         organiser.organise(paths).expect("should organise");
 
         assert!(
-            output_dir.exists(),
-            "output dir should have been created: {}",
-            output_dir.display()
-        );
-        assert!(
-            source_file.exists(),
-            "source file should still exist: {}",
-            source_file.display()
+            output_file_1.exists(),
+            "output file should have been created: {}",
+            output_file_1.display()
         );
         assert!(
-            output_file.exists(),
+            output_file_2.exists(),
             "output file should have been created: {}",
-            output_file.display()
+            output_file_2.display()
+        );
+        assert_eq!(
+            fs::read_dir(&series_dir)
+                .expect("should read series dir")
+                .count(),
+            1,
+            "series dir should only contain the volume dir"
         );
     }
 
     #[test]
-    fn test_organise_dry_run() {
+    fn test_organise_template() {
         let dir = TempDir::new().expect("should create temp dir");
         let source_dir = dir.child("source");
         let output_dir = dir.child("output");
 
-        let series = "Test";
-        let series_dir = output_dir.join(series);
-
-        let name = "Test 001.cbz";
-        let source_file = source_dir.join(name);
-        let output_file = output_dir.join(name);
+        let source_file = source_dir.join("Test 001 Title (2024).cbz");
+        let output_file = output_dir.join("Test #001 - Title (2024).cbz");
 
         std::fs::create_dir_all(&source_dir).expect("should create source dir");
         fs::write(&source_file, "").expect("should create source file");
 
+        let settings = Settings {
+            output: output_dir,
+            template: "{series} #{number:03}[ - {title}][ ({year})]"
+                .parse()
+                .expect("should parse template"),
+            ..Default::default()
+        };
+
+        let organiser = Organiser::new(settings, Default::default());
+
+        let paths = vec![source_dir];
+
+        organiser.organise(paths).expect("should organise");
+
         assert!(
-            !output_dir.exists(),
-            "output dir should not exist before organise: {}",
-            output_dir.display()
+            output_file.exists(),
+            "output file should have been created: {}",
+            output_file.display()
+        );
+    }
+
+    #[test]
+    fn test_organise_layout() {
+        let dir = TempDir::new().expect("should create temp dir");
+        let source_dir = dir.child("source");
+        let output_dir = dir.child("output");
+
+        let source_file_1 = source_dir.join("Test v2 001.cbz");
+        let source_file_2 = source_dir.join("Test 001.cbr");
+        let output_file_1 = output_dir.join("cbz/Test/vol2/Test v2 001.cbz");
+        let output_file_2 = output_dir.join("cbr/Test/Test 001.cbr");
+
+        std::fs::create_dir_all(&source_dir).expect("should create source dir");
+        fs::write(&source_file_1, "").expect("should create first source file");
+        fs::write(&source_file_2, "").expect("should create second source file");
+
+        let settings = Settings {
+            output: output_dir,
+            layout: Some(
+                "{format}/{series}[/vol{volume}]"
+                    .parse()
+                    .expect("should parse layout"),
+            ),
+            ..Default::default()
+        };
+
+        let organiser = Organiser::new(settings, Default::default());
+
+        let paths = vec![source_dir];
+
+        organiser.organise(paths).expect("should organise");
+
+        assert!(
+            output_file_1.exists(),
+            "output file should have been created: {}",
+            output_file_1.display()
         );
         assert!(
-            !series_dir.exists(),
-            "series dir should not exist before organise: {}",
-            series_dir.display()
+            output_file_2.exists(),
+            "output file should have been created: {}",
+            output_file_2.display()
+        );
+    }
+
+    #[test]
+    fn test_organise_read_info() {
+        use std::io::Write;
+        use zip::{write::SimpleFileOptions, ZipWriter};
+
+        let dir = TempDir::new().expect("should create temp dir");
+        let source_dir = dir.child("source");
+        let output_dir = dir.child("output");
+
+        let source_file_1 = source_dir.join("[Scans] unnamed.cbz");
+        let source_file_2 = source_dir.join("Test 002 (2020).cbz");
+        let output_file_1 = output_dir.join("Test 001 Title (2024).cbz");
+        let output_file_2 = output_dir.join("Test 002 Title (2020).cbz");
+
+        std::fs::create_dir_all(&source_dir).expect("should create source dir");
+
+        let info = [
+            (
+                &source_file_1,
+                "<ComicInfo><Series>Test</Series><Number>1</Number>",
+            ),
+            (
+                &source_file_2,
+                "<ComicInfo><Series>Test</Series><Number>3</Number>",
+            ),
+        ];
+
+        for (path, xml) in info {
+            let mut zip =
+                ZipWriter::new(fs::File::create(path).expect("should create source file"));
+            zip.start_file("ComicInfo.xml", SimpleFileOptions::default())
+                .expect("should start comic info");
+            write!(
+                zip,
+                "{xml}<Title>Title</Title><Year>2024</Year></ComicInfo>"
+            )
+            .expect("should write comic info");
+            zip.finish().expect("should finish source file");
+        }
+
+        let settings = Settings {
+            output: output_dir,
+            read_info: true,
+            ..Default::default()
+        };
+
+        let organiser = Organiser::new(settings, Default::default());
+
+        let paths = vec![source_dir];
+
+        organiser.organise(paths).expect("should organise");
+
+        assert!(
+            output_file_1.exists(),
+            "output file should have been created: {}",
+            output_file_1.display()
+        );
+        assert!(
+            output_file_2.exists(),
+            "output file should have been created: {}",
+            output_file_2.display()
+        );
+    }
+
+    #[test]
+    fn test_organise_write_info() {
+        use std::io::Read;
+        use zip::{ZipArchive, ZipWriter};
+
+        let dir = TempDir::new().expect("should create temp dir");
+        let source_dir = dir.child("source");
+        let output_dir = dir.child("output");
+
+        let source_file = source_dir.join("Test v2 #1 of 3 (2024).cbz");
+        let output_file = output_dir.join("Test v2 001 of 3 (2024).cbz");
+
+        std::fs::create_dir_all(&source_dir).expect("should create source dir");
+
+        let file = fs::File::create(&source_file).expect("should create source file");
+        ZipWriter::new(file)
+            .finish()
+            .expect("should finish source file");
+
+        let settings = Settings {
+            output: output_dir,
+            write_info: true,
+            ..Default::default()
+        };
+
+        let organiser = Organiser::new(settings, Default::default());
+
+        let paths = vec![source_dir];
+
+        organiser.organise(paths).expect("should organise");
+
+        let file = fs::File::open(&output_file).expect("should open output file");
+        let mut archive = ZipArchive::new(file).expect("should read output file");
+        let mut xml = String::new();
+        archive
+            .by_name("ComicInfo.xml")
+            .expect("should contain comic info")
+            .read_to_string(&mut xml)
+            .expect("should read comic info");
+
+        for element in [
+            "<Series>Test</Series>",
+            "<Volume>2</Volume>",
+            "<Number>1</Number>",
+            "<Count>3</Count>",
+            "<Year>2024</Year>",
+        ] {
+            assert!(xml.contains(element), "comic info should contain {element}");
+        }
+
+        let mut archive =
+            ZipArchive::new(fs::File::open(&source_file).expect("should open source"))
+                .expect("should read source file");
+        assert!(
+            archive.by_name("ComicInfo.xml").is_err(),
+            "source file should not be changed"
+        );
+    }
+
+    #[test]
+    fn test_organise_write_info_fails() {
+        let dir = TempDir::new().expect("should create temp dir");
+        let source_dir = dir.child("source");
+        let output_dir = dir.child("output");
+
+        let source_file = source_dir.join("Test 001.cbz");
+        let output_file = output_dir.join("Test 001.cbz");
+
+        std::fs::create_dir_all(&source_dir).expect("should create source dir");
+        fs::write(&source_file, "not a zip").expect("should create source file");
+
+        let settings = Settings {
+            output: output_dir,
+            write_info: true,
+            transfer: Transfer::Move,
+            ..Default::default()
+        };
+
+        let report = Organiser::new(settings, Default::default())
+            .organise(vec![source_dir])
+            .expect("should organise");
+
+        assert!(output_file.exists(), "file should still be moved");
+        assert_eq!(report.counts.moved, 1, "file should be reported as moved");
+        assert_eq!(
+            report.counts.failed, 0,
+            "file should not be reported as failed"
+        );
+        assert_eq!(
+            report.files[0].destination.as_deref(),
+            Some(output_file.as_path()),
+            "record should keep the destination"
         );
+        assert!(
+            report.files[0]
+                .reason
+                .as_deref()
+                .is_some_and(|reason| reason.starts_with("comic info not written")),
+            "record should give the reason"
+        );
+    }
+
+    #[test]
+    fn test_organise_convert() {
+        let dir = TempDir::new().expect("should create temp dir");
+        let source_dir = dir.child("source");
+        let output_dir = dir.child("output");
+
+        let source_file_1 = source_dir.join("Test 001.cbt");
+        let source_file_2 = source_dir.join("Test 002.cbz");
+        let output_file_1 = output_dir.join("Test 001.cbz");
+        let output_file_2 = output_dir.join("Test 002.cbz");
+
+        std::fs::create_dir_all(&source_dir).expect("should create source dir");
+
+        let file = fs::File::create(&source_file_1).expect("should create first source file");
+        let mut builder = tar::Builder::new(file);
+        let mut header = tar::Header::new_gnu();
+        header.set_size(4);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "001.jpg", "page".as_bytes())
+            .expect("should append page");
+        builder.finish().expect("should finish first source file");
+
+        fs::write(&source_file_2, "").expect("should create second source file");
 
         let settings = Settings {
             output: output_dir.clone(),
-            series: true,
-            move_files: true,
-            dry_run: true,
-            force: true,
+            convert: true,
+            transfer: Transfer::Move,
             ..Default::default()
         };
 
         let organiser = Organiser::new(settings, Default::default());
 
-        let paths = vec![source_dir.clone()];
+        let paths = vec![source_dir];
 
         organiser.organise(paths).expect("should organise");
 
         assert!(
-            !output_dir.exists(),
-            "output dir should not be created: {}",
-            output_dir.display()
+            !source_file_1.exists(),
+            "converted source file should be removed: {}",
+            source_file_1.display()
         );
         assert!(
-            !series_dir.exists(),
-            "series dir should not be created: {}",
-            series_dir.display()
+            !source_file_2.exists(),
+            "moved source file should be removed: {}",
+            source_file_2.display()
         );
+
+        let file = fs::File::open(&output_file_1).expect("should open converted file");
+        let archive = zip::ZipArchive::new(file).expect("should read converted file");
+        assert_eq!(archive.file_names().collect::<Vec<_>>(), ["001.jpg"]);
+
         assert!(
-            source_file.exists(),
-            "source file should still exist: {}",
-            source_file.display()
+            output_file_2.exists(),
+            "output file should have been created: {}",
+            output_file_2.display()
+        );
+        assert_eq!(
+            fs::read_dir(&output_dir)
+                .expect("should read output dir")
+                .count(),
+            2,
+            "temporary files should be removed"
         );
+    }
+
+    #[test]
+    fn test_organise_write_info_linked() {
+        use zip::ZipWriter;
+
+        let dir = TempDir::new().expect("should create temp dir");
+        let source_dir = dir.child("source");
+        let output_dir = dir.child("output");
+
+        let source_file = source_dir.join("Test 001.cbz");
+        let output_file = output_dir.join("Test 001.cbz");
+
+        std::fs::create_dir_all(&source_dir).expect("should create source dir");
+
+        let file = fs::File::create(&source_file).expect("should create source file");
+        ZipWriter::new(file)
+            .finish()
+            .expect("should finish source file");
+
+        let settings = Settings {
+            output: output_dir,
+            write_info: true,
+            transfer: Transfer::Symlink,
+            ..Default::default()
+        };
+
+        let organiser = Organiser::new(settings, Default::default());
+
+        let report = organiser
+            .organise(vec![source_dir])
+            .expect("should organise");
+
         assert!(
-            !output_file.exists(),
-            "output file should not be created: {}",
+            output_file
+                .symlink_metadata()
+                .expect("should read output file metadata")
+                .is_symlink(),
+            "output file should still be a symlink"
+        );
+        assert_eq!(report.counts.linked, 1, "one file should be linked");
+    }
+
+    #[test]
+    #[cfg(not(feature = "cbr"))]
+    fn test_organise_convert_unsupported() {
+        let dir = TempDir::new().expect("should create temp dir");
+        let source_dir = dir.child("source");
+        let output_dir = dir.child("output");
+
+        let source_file = source_dir.join("Test 001.cbr");
+        let output_file = output_dir.join("Test 001.cbr");
+
+        std::fs::create_dir_all(&source_dir).expect("should create source dir");
+        fs::write(&source_file, "").expect("should create source file");
+
+        let settings = Settings {
+            output: output_dir.clone(),
+            convert: true,
+            ..Default::default()
+        };
+
+        let organiser = Organiser::new(settings, Default::default());
+
+        let report = organiser
+            .organise(vec![source_dir])
+            .expect("should organise");
+
+        assert!(
+            output_file.exists(),
+            "unsupported archive should be transferred unconverted: {}",
             output_file.display()
         );
+        assert_eq!(report.counts.copied, 1, "one file should be copied");
+        assert_eq!(report.counts.failed, 0, "no files should fail");
+    }
+
+    #[test]
+    fn test_organise_fix_extension() {
+        let dir = TempDir::new().expect("should create temp dir");
+        let source_dir = dir.child("source");
+        let output_dir = dir.child("output");
+
+        let source_file_1 = source_dir.join("Test 001.cbr");
+        let source_file_2 = source_dir.join("Test 002.cbz");
+        let output_file_1 = output_dir.join("Test 001.cbz");
+        let output_file_2 = output_dir.join("Test 002.cbr");
+        let output_file_3 = output_dir.join("Test 002.cbz");
+
+        std::fs::create_dir_all(&source_dir).expect("should create source dir");
+        fs::write(&source_file_1, b"PK\x03\x04").expect("should create first source file");
+        fs::write(&source_file_2, b"Rar!\x1a\x07\x00").expect("should create second source file");
+
+        let settings = Settings {
+            output: output_dir,
+            fix_extension: true,
+            ..Default::default()
+        };
+
+        let organiser = Organiser::new(settings, Default::default());
+
+        let paths = vec![source_dir];
+
+        organiser.organise(paths).expect("should organise");
+
+        assert!(
+            output_file_1.exists(),
+            "output file should have been created: {}",
+            output_file_1.display()
+        );
+        assert!(
+            output_file_2.exists(),
+            "output file should have been created: {}",
+            output_file_2.display()
+        );
+        assert!(
+            !output_file_3.exists(),
+            "output file should not keep the wrong extension: {}",
+            output_file_3.display()
+        );
+    }
+
+    #[test]
+    fn test_organise_creates_output_dir() {
+        let dir = TempDir::new().expect("should create temp dir");
+        let source_dir = dir.child("source");
+        let output_dir = dir.child("output");
+
+        let name = "Test 001.cbz";
+        let source_file = source_dir.join(name);
+        let output_file = output_dir.join(name);
+
+        std::fs::create_dir_all(&source_dir).expect("should create source dir");
+        fs::write(&source_file, "").expect("should create source file");
+
+        assert!(
+            !output_dir.exists(),
+            "output dir should not exist before organise: {}",
+            output_dir.display()
+        );
+
+        let settings = Settings {
+            output: output_dir.clone(),
+            ..Default::default()
+        };
+
+        let organiser = Organiser::new(settings, Default::default());
+
+        let paths = vec![source_dir.clone()];
+
+        organiser.organise(paths).expect("should organise");
+
+        assert!(
+            output_dir.exists(),
+            "output dir should have been created: {}",
+            output_dir.display()
+        );
+        assert!(
+            source_file.exists(),
+            "source file should still exist: {}",
+            source_file.display()
+        );
+        assert!(
+            output_file.exists(),
+            "output file should have been created: {}",
+            output_file.display()
+        );
+    }
+
+    #[test]
+    fn test_organise_dry_run() {
+        let dir = TempDir::new().expect("should create temp dir");
+        let source_dir = dir.child("source");
+        let output_dir = dir.child("output");
+
+        let series = "Test";
+        let series_dir = output_dir.join(series);
+
+        let name = "Test 001.cbz";
+        let source_file = source_dir.join(name);
+        let output_file = output_dir.join(name);
+
+        std::fs::create_dir_all(&source_dir).expect("should create source dir");
+        fs::write(&source_file, "").expect("should create source file");
+
+        assert!(
+            !output_dir.exists(),
+            "output dir should not exist before organise: {}",
+            output_dir.display()
+        );
+        assert!(
+            !series_dir.exists(),
+            "series dir should not exist before organise: {}",
+            series_dir.display()
+        );
+
+        let settings = Settings {
+            output: output_dir.clone(),
+            series: true,
+            transfer: Transfer::Move,
+            dry_run: true,
+            conflict: Conflict::Overwrite,
+            ..Default::default()
+        };
+
+        let organiser = Organiser::new(settings, Default::default());
+
+        let paths = vec![source_dir.clone()];
+
+        organiser.organise(paths).expect("should organise");
+
+        assert!(
+            !output_dir.exists(),
+            "output dir should not be created: {}",
+            output_dir.display()
+        );
+        assert!(
+            !series_dir.exists(),
+            "series dir should not be created: {}",
+            series_dir.display()
+        );
+        assert!(
+            source_file.exists(),
+            "source file should still exist: {}",
+            source_file.display()
+        );
+        assert!(
+            !output_file.exists(),
+            "output file should not be created: {}",
+            output_file.display()
+        );
+    }
+
+    #[test]
+    fn test_organise_undo_write_info_fails() {
+        let dir = TempDir::new().expect("should create temp dir");
+        let source_dir = dir.child("source");
+        let output_dir = dir.child("output");
+
+        let source_file = source_dir.join("Test 001.cbz");
+
+        std::fs::create_dir_all(&source_dir).expect("should create source dir");
+        fs::write(&source_file, "not a zip").expect("should create source file");
+
+        let settings = Settings {
+            output: output_dir.clone(),
+            write_info: true,
+            transfer: Transfer::Move,
+            journal: true,
+            backup: true,
+            ..Default::default()
+        };
+
+        Organiser::new(settings, Default::default())
+            .organise(vec![source_dir])
+            .expect("should organise");
+
+        let journal = Journal::latest(&output_dir)
+            .expect("should find journal")
+            .expect("journal should have been written");
+        let backups = journal.with_extension("");
+
+        assert!(
+            !backups.exists() || backups.read_dir().expect("should read backups").count() == 0,
+            "unrecorded snapshot should have been removed"
+        );
+
+        crate::organise::undo(&journal, false).expect("should undo");
+
+        assert!(
+            source_file.exists(),
+            "source file should have been restored"
+        );
+        assert!(!backups.exists(), "backups dir should have been removed");
+    }
+
+    #[test]
+    fn test_organise_undo_move() {
+        let dir = TempDir::new().expect("should create temp dir");
+        let source_dir = dir.child("source");
+        let output_dir = dir.child("output");
+        let series_dir = output_dir.join("Test");
+
+        let name = "Test 001.cbz";
+        let source_file = source_dir.join(name);
+        let output_file = series_dir.join(name);
+
+        std::fs::create_dir_all(&source_dir).expect("should create source dir");
+        fs::write(&source_file, "contents").expect("should create source file");
+
+        let settings = Settings {
+            output: output_dir.clone(),
+            series: true,
+            transfer: Transfer::Move,
+            journal: true,
+            backup: true,
+            ..Default::default()
+        };
+
+        let organiser = Organiser::new(settings, Default::default());
+
+        organiser
+            .organise(vec![source_dir])
+            .expect("should organise");
+
+        assert!(output_file.exists(), "output file should have been created");
+
+        let journal = Journal::latest(&output_dir)
+            .expect("should find journal")
+            .expect("journal should have been written");
+
+        crate::organise::undo(&journal, false).expect("should undo");
+
+        assert!(
+            source_file.exists(),
+            "source file should have been restored: {}",
+            source_file.display()
+        );
+        assert!(
+            !series_dir.exists(),
+            "series dir should have been removed: {}",
+            series_dir.display()
+        );
+        assert_eq!(
+            Journal::latest(&output_dir).expect("should find journals"),
+            None,
+            "journal should be marked as undone"
+        );
+    }
+
+    #[test]
+    fn test_organise_undo_overwrite() {
+        let dir = TempDir::new().expect("should create temp dir");
+        let source_dir = dir.child("source");
+        let output_dir = dir.child("output");
+
+        let name = "Test 001.cbz";
+        let source_file = source_dir.join(name);
+        let output_file = output_dir.join(name);
+
+        std::fs::create_dir_all(&source_dir).expect("should create source dir");
+        std::fs::create_dir_all(&output_dir).expect("should create output dir");
+        fs::write(&source_file, "new").expect("should create source file");
+        fs::write(&output_file, "old").expect("should create existing file");
+
+        let settings = Settings {
+            output: output_dir.clone(),
+            conflict: Conflict::Overwrite,
+            journal: true,
+            backup: true,
+            ..Default::default()
+        };
+
+        let organiser = Organiser::new(settings, Default::default());
+
+        organiser
+            .organise(vec![source_file.clone()])
+            .expect("should organise");
+
+        assert_eq!(
+            fs::read_to_string(&output_file).expect("should read output file"),
+            "new",
+            "output file should have been overwritten"
+        );
+
+        let journal = Journal::latest(&output_dir)
+            .expect("should find journal")
+            .expect("journal should have been written");
+
+        crate::organise::undo(&journal, false).expect("should undo");
+
+        assert!(source_file.exists(), "source file should be kept");
+        assert_eq!(
+            fs::read_to_string(&output_file).expect("should read output file"),
+            "old",
+            "overwritten file should have been restored"
+        );
+    }
+
+    #[test]
+    fn test_organise_no_backup() {
+        let dir = TempDir::new().expect("should create temp dir");
+        let source_dir = dir.child("source");
+        let output_dir = dir.child("output");
+
+        let name = "Test 001.cbz";
+        let source_file = source_dir.join(name);
+        let output_file = output_dir.join(name);
+
+        std::fs::create_dir_all(&source_dir).expect("should create source dir");
+        std::fs::create_dir_all(&output_dir).expect("should create output dir");
+        fs::write(&source_file, "new").expect("should create source file");
+        fs::write(&output_file, "old").expect("should create existing file");
+
+        let settings = Settings {
+            output: output_dir.clone(),
+            conflict: Conflict::Overwrite,
+            journal: true,
+            backup: false,
+            ..Default::default()
+        };
+
+        let organiser = Organiser::new(settings, Default::default());
+
+        organiser
+            .organise(vec![source_file.clone()])
+            .expect("should organise");
+
+        assert_eq!(
+            fs::read_to_string(&output_file).expect("should read output file"),
+            "new",
+            "output file should have been overwritten"
+        );
+
+        let entries: Vec<_> = fs::read_dir(output_dir.join(Journal::DIR))
+            .expect("should read journal dir")
+            .map(|entry| entry.expect("should read journal entry").path())
+            .collect();
+
+        assert!(
+            entries.iter().all(|path| path.is_file()),
+            "no backups should be kept: {entries:?}"
+        );
+    }
+
+    #[test]
+    fn test_organise_relative_symlink() {
+        let dir = TempDir::new().expect("should create temp dir");
+        let source_dir = dir.child("source");
+        let output_dir = dir.child("output");
+
+        let name = "Test 001.cbz";
+        let source_file = source_dir.join(name);
+        let output_file = output_dir.join(name);
+
+        std::fs::create_dir_all(&source_dir).expect("should create source dir");
+        fs::write(&source_file, "contents").expect("should create source file");
+
+        let settings = Settings {
+            output: output_dir.clone(),
+            transfer: Transfer::RelativeSymlink,
+            journal: true,
+            backup: true,
+            ..Default::default()
+        };
+
+        let organiser = Organiser::new(settings, Default::default());
+
+        organiser
+            .organise(vec![source_file.clone()])
+            .expect("should organise");
+
+        assert!(source_file.exists(), "source file should be kept");
+        assert_eq!(
+            fs::read_link(&output_file).expect("output file should be a symlink"),
+            PathBuf::from("../source").join(name),
+            "symlink should be relative to the output dir"
+        );
+
+        let journal = Journal::latest(&output_dir)
+            .expect("should find journal")
+            .expect("journal should have been written");
+
+        crate::organise::undo(&journal, false).expect("should undo");
+
+        assert!(
+            output_file.symlink_metadata().is_err(),
+            "symlink should have been removed"
+        );
+        assert!(source_file.exists(), "source file should be kept");
+    }
+
+    #[test]
+    fn test_organise_cleans_temp_files() {
+        let dir = TempDir::new().expect("should create temp dir");
+        let source_dir = dir.child("source");
+        let output_dir = dir.child("output");
+
+        let name = "Test 001.cbz";
+        let source_file = source_dir.join(name);
+        let output_file = output_dir.join(name);
+        let temp_file = temp::path(&output_file);
+
+        std::fs::create_dir_all(&source_dir).expect("should create source dir");
+        std::fs::create_dir_all(&output_dir).expect("should create output dir");
+        fs::write(&source_file, "contents").expect("should create source file");
+        fs::write(&temp_file, "partial").expect("should create leftover temp file");
+
+        let settings = Settings {
+            output: output_dir,
+            ..Default::default()
+        };
+
+        let organiser = Organiser::new(settings, Default::default());
+
+        organiser
+            .organise(vec![source_dir])
+            .expect("should organise");
+
+        assert!(
+            !temp_file.exists(),
+            "leftover temp file should have been removed: {}",
+            temp_file.display()
+        );
+        assert_eq!(
+            fs::read_to_string(&output_file).expect("should read output file"),
+            "contents",
+            "output file should have been copied"
+        );
+    }
+
+    #[test]
+    fn test_organise_move_to_conflicts() {
+        let dir = TempDir::new().expect("should create temp dir");
+        let source_dir = dir.child("source");
+        let output_dir = dir.child("output");
+
+        let name = "Test 001.cbz";
+        let source_file = source_dir.join(name);
+        let output_file = output_dir.join(name);
+        let conflict_file = output_dir.join(Conflict::DIR).join(name);
+
+        std::fs::create_dir_all(&source_dir).expect("should create source dir");
+        std::fs::create_dir_all(&output_dir).expect("should create output dir");
+        fs::write(&source_file, "new").expect("should create source file");
+        fs::write(&output_file, "existing").expect("should create existing file");
+
+        let hour_ago = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+        fs::File::options()
+            .write(true)
+            .open(&output_file)
+            .and_then(|file| file.set_modified(hour_ago))
+            .expect("should set existing file time");
+
+        let settings = || Settings {
+            output: output_dir.clone(),
+            conflict: Conflict::MoveToConflicts,
+            journal: true,
+            ..Default::default()
+        };
+
+        let organiser = Organiser::new(settings(), Default::default());
+
+        organiser
+            .organise(vec![source_file.clone()])
+            .expect("should organise");
+
+        assert_eq!(
+            fs::read_to_string(&output_file).expect("should read new file"),
+            "new",
+            "newer file should replace the existing file"
+        );
+        assert_eq!(
+            fs::read_to_string(&conflict_file).expect("should read conflict file"),
+            "existing",
+            "older existing file should be in the conflicts dir"
+        );
+
+        let journal = Journal::latest(&output_dir)
+            .expect("should find journal")
+            .expect("journal should have been written");
+
+        crate::organise::undo(&journal, false).expect("should undo");
+
+        assert_eq!(
+            fs::read_to_string(&output_file).expect("should read existing file"),
+            "existing",
+            "existing file should have been restored"
+        );
+        assert!(!conflict_file.exists(), "conflict file should be gone");
+
+        fs::File::options()
+            .write(true)
+            .open(&source_file)
+            .and_then(|file| file.set_modified(hour_ago - std::time::Duration::from_secs(3600)))
+            .expect("should set source file time");
+
+        let organiser = Organiser::new(settings(), Default::default());
+
+        organiser
+            .organise(vec![source_file])
+            .expect("should organise");
+
+        assert_eq!(
+            fs::read_to_string(&output_file).expect("should read existing file"),
+            "existing",
+            "newer existing file should be kept"
+        );
+        assert_eq!(
+            fs::read_to_string(&conflict_file).expect("should read conflict file"),
+            "new",
+            "older new file should be in the conflicts dir"
+        );
+    }
+
+    #[test]
+    fn test_organise_filter() {
+        let dir = TempDir::new().expect("should create temp dir");
+        let source_dir = dir.child("source");
+        let output_dir = dir.child("output");
+        let nested_dir = source_dir.join("a").join("b");
+
+        let names = ["Test 001.cbz", "Test 001.nfo", "Test 002.cbz.part"];
+
+        std::fs::create_dir_all(&nested_dir).expect("should create nested dir");
+        for name in names {
+            fs::write(source_dir.join(name), "").expect("should create source file");
+        }
+        fs::write(nested_dir.join("Test 003.cbz"), "").expect("should create nested file");
+
+        let settings = Settings {
+            output: output_dir.clone(),
+            recursive: true,
+            max_depth: Some(1),
+            filter: Filter::new(
+                &["*.cbz".parse().expect("should parse include")],
+                &["*.part".parse().expect("should parse exclude")],
+            ),
+            ..Default::default()
+        };
+
+        let organiser = Organiser::new(settings, Default::default());
+
+        organiser
+            .organise(vec![source_dir])
+            .expect("should organise");
+
+        let mut organised: Vec<_> = fs::read_dir(&output_dir)
+            .expect("should read output dir")
+            .map(|entry| entry.expect("should read entry").file_name())
+            .collect();
+        organised.sort();
+
+        assert_eq!(
+            organised,
+            ["Test 001.cbz"],
+            "only included files within the max depth should be organised"
+        );
+    }
+
+    #[test]
+    fn test_organise_report() {
+        let dir = TempDir::new().expect("should create temp dir");
+        let source_dir = dir.child("source");
+        let output_dir = dir.child("output");
+
+        std::fs::create_dir_all(&source_dir).expect("should create source dir");
+        std::fs::create_dir_all(&output_dir).expect("should create output dir");
+        fs::write(source_dir.join("Test 001.cbz"), "").expect("should create source file");
+        fs::write(source_dir.join("Test 002.cbz"), "").expect("should create source file");
+        fs::write(source_dir.join("notes.txt"), "").expect("should create unparseable file");
+        fs::write(output_dir.join("Test 002.cbz"), "").expect("should create existing file");
+
+        let settings = Settings {
+            output: output_dir.clone(),
+            ..Default::default()
+        };
+
+        let organiser = Organiser::new(settings, Default::default());
+
+        let report = organiser
+            .organise(vec![source_dir.clone()])
+            .expect("should organise");
+
+        assert_eq!(report.counts.copied, 1, "one file should be copied");
+        assert_eq!(report.counts.skipped, 1, "existing file should be skipped");
+        assert_eq!(report.counts.unparseable, 1, "notes should be unparseable");
+        assert_eq!(report.counts.failed, 0, "no files should fail");
+        assert_eq!(report.files.len(), 3);
+
+        let copied = report
+            .files
+            .iter()
+            .find(|record| record.outcome == Outcome::Copied)
+            .expect("should have a copied file");
+        assert_eq!(copied.source, source_dir.join("Test 001.cbz"));
+        assert_eq!(copied.destination, Some(output_dir.join("Test 001.cbz")));
+    }
+
+    #[test]
+    fn test_organise_prune_empty() {
+        let dir = TempDir::new().expect("should create temp dir");
+        let source_dir = dir.child("source");
+        let output_dir = dir.child("output");
+        let emptied_dir = source_dir.join("a").join("b");
+        let kept_dir = source_dir.join("c");
+
+        std::fs::create_dir_all(&emptied_dir).expect("should create emptied dir");
+        std::fs::create_dir_all(&kept_dir).expect("should create kept dir");
+        fs::write(emptied_dir.join("Test 001.cbz"), "").expect("should create source file");
+        fs::write(emptied_dir.join(".DS_Store"), "").expect("should create junk file");
+        fs::write(kept_dir.join("Test 002.cbz"), "").expect("should create source file");
+        fs::write(kept_dir.join("notes.txt"), "").expect("should create unparseable file");
+
+        let settings = |dry_run| Settings {
+            output: output_dir.clone(),
+            transfer: Transfer::Move,
+            recursive: true,
+            prune_empty: true,
+            junk: vec![Glob::new(".DS_Store")
+                .expect("should parse junk")
+                .compile_matcher()],
+            dry_run,
+            journal: true,
+            backup: true,
+            ..Default::default()
+        };
+
+        Organiser::new(settings(true), Default::default())
+            .organise(vec![source_dir.clone()])
+            .expect("should organise");
+
+        assert!(emptied_dir.exists(), "dry run should not remove dirs");
+
+        Organiser::new(settings(false), Default::default())
+            .organise(vec![source_dir.clone()])
+            .expect("should organise");
+
+        assert!(
+            !source_dir.join("a").exists(),
+            "emptied dirs should be removed bottom-up"
+        );
+        assert!(
+            kept_dir.exists(),
+            "dir with unparseable file should be kept"
+        );
+        assert!(source_dir.exists(), "input dir should never be removed");
+
+        let journal = Journal::latest(&output_dir)
+            .expect("should find journal")
+            .expect("journal should have been written");
+
+        crate::organise::undo(&journal, false).expect("should undo");
+
+        assert!(
+            emptied_dir.join("Test 001.cbz").exists(),
+            "undo should restore the moved file"
+        );
+        assert!(
+            emptied_dir.join(".DS_Store").exists(),
+            "undo should restore the junk file"
+        );
+    }
+
+    #[test]
+    fn test_organise_preserve_times() {
+        let dir = TempDir::new().expect("should create temp dir");
+        let source_dir = dir.child("source");
+        let output_dir = dir.child("output");
+
+        std::fs::create_dir_all(&source_dir).expect("should create source dir");
+
+        let day_ago = std::time::SystemTime::now() - Duration::from_secs(24 * 60 * 60);
+
+        for name in ["Test 001.cbz", "Test 002.cbz"] {
+            let file = source_dir.join(name);
+            fs::write(&file, "").expect("should create source file");
+            fs::File::options()
+                .write(true)
+                .open(&file)
+                .expect("should open source file")
+                .set_modified(day_ago)
+                .expect("should set modified time");
+        }
+
+        let settings = Settings {
+            output: output_dir.clone(),
+            preserve: Preserve {
+                times: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let organiser = Organiser::new(settings, Default::default());
+
+        organiser
+            .organise(vec![source_dir.join("Test 001.cbz")])
+            .expect("should organise");
+
+        let modified = |path: PathBuf| {
+            path.metadata()
+                .expect("should get metadata")
+                .modified()
+                .expect("should get modified time")
+        };
+
+        assert_eq!(
+            modified(output_dir.join("Test 001.cbz")),
+            day_ago,
+            "copied file should keep its modification time"
+        );
+
+        let settings = Settings {
+            output: output_dir.clone(),
+            ..Default::default()
+        };
+
+        Organiser::new(settings, Default::default())
+            .organise(vec![source_dir.join("Test 002.cbz")])
+            .expect("should organise");
+
+        assert_ne!(
+            modified(output_dir.join("Test 002.cbz")),
+            day_ago,
+            "copied file should have a new modification time by default"
+        );
+    }
+
+    #[test]
+    fn test_organise_index() {
+        let dir = TempDir::new().expect("should create temp dir");
+        let source_dir = dir.child("source");
+        let output_dir = dir.child("output");
+
+        std::fs::create_dir_all(&source_dir).expect("should create source dir");
+        fs::write(source_dir.join("Test 001.cbz"), "").expect("should create source file");
+
+        let settings = |rescan| Settings {
+            output: output_dir.clone(),
+            index: true,
+            rescan,
+            ..Default::default()
+        };
+
+        let report = Organiser::new(settings(false), Default::default())
+            .organise(vec![source_dir.clone()])
+            .expect("should organise");
+        assert_eq!(report.counts.copied, 1, "new file should be copied");
+
+        let index = Index::load(&output_dir).expect("should load index");
+        let files: Vec<_> = index.files().collect();
+        assert_eq!(files.len(), 1, "copied file should be indexed");
+        assert_eq!(files[0].meta.series, "Test");
+        assert_eq!(
+            files[0].destination,
+            absolute(&output_dir.join("Test 001.cbz"))
+        );
+
+        let report = Organiser::new(settings(false), Default::default())
+            .organise(vec![source_dir.clone()])
+            .expect("should organise");
+        assert_eq!(
+            report.files[0].reason.as_deref(),
+            Some("unchanged since last run"),
+            "unchanged file should be skipped"
+        );
+
+        let report = Organiser::new(settings(true), Default::default())
+            .organise(vec![source_dir.clone()])
+            .expect("should organise");
+        assert_eq!(
+            report.files[0].reason.as_deref(),
+            Some("file already exists (skip)"),
+            "rescan should organise the file again"
+        );
+
+        let settings = Settings {
+            transfer: Transfer::Move,
+            conflict: Conflict::Overwrite,
+            ..settings(false)
+        };
+
+        let report = Organiser::new(settings, Default::default())
+            .organise(vec![source_dir.clone()])
+            .expect("should organise");
+        assert_eq!(
+            report.counts.overwritten, 1,
+            "file copied before should be organised again when moving"
+        );
+        assert!(
+            !source_dir.join("Test 001.cbz").exists(),
+            "source should have been moved"
+        );
+    }
+
+    #[test]
+    fn test_organise_in_place() {
+        let dir = TempDir::new().expect("should create temp dir");
+        let source_dir = dir.child("source");
+        let series_dir = source_dir.join("Test");
+
+        std::fs::create_dir_all(&series_dir).expect("should create series dir");
+        fs::write(source_dir.join("Test 1.cbz"), "").expect("should create source file");
+        fs::write(series_dir.join("Test 002.cbz"), "").expect("should create organised file");
+
+        let settings = Settings {
+            output: dir.child("state"),
+            in_place: true,
+            transfer: Transfer::Move,
+            series: true,
+            recursive: true,
+            journal: true,
+            index: true,
+            ..Default::default()
+        };
+
+        let report = Organiser::new(settings, Default::default())
+            .organise(vec![source_dir.clone()])
+            .expect("should organise");
+
+        assert!(
+            series_dir.join("Test 001.cbz").exists(),
+            "file should be renamed into a series dir next to it"
+        );
+        assert!(
+            !source_dir.join("Test 1.cbz").exists(),
+            "file should be moved"
+        );
+        assert!(
+            !series_dir.join("Test").exists(),
+            "files already in a series dir should not be nested again"
+        );
+        assert!(
+            dir.child("state").join(Index::PATH).exists(),
+            "index should be kept in the output dir"
+        );
+        assert!(
+            dir.child("state").join(Journal::DIR).exists(),
+            "journal should be kept in the output dir"
+        );
+        assert!(
+            !source_dir.join(".cyborg").exists() && !series_dir.join(".cyborg").exists(),
+            "state should not be kept next to the files"
+        );
+        assert!(
+            !dir.child("state").join("Test").exists(),
+            "files should not be organised into the output dir"
+        );
+        assert!(
+            report
+                .files
+                .iter()
+                .any(|record| record.reason.as_deref() == Some("already organised")),
+            "organised file should be skipped"
+        );
+    }
+
+    #[test]
+    fn test_organise_in_place_output_root() {
+        let dir = TempDir::new().expect("should create temp dir");
+        let library = dir.child("library");
+
+        std::fs::create_dir_all(&library).expect("should create library dir");
+        fs::write(library.join("Test 1.cbz"), "").expect("should create source file");
+
+        let settings = || Settings {
+            output: library.clone(),
+            in_place: true,
+            transfer: Transfer::Move,
+            series: true,
+            recursive: true,
+            journal: true,
+            backup: true,
+            index: true,
+            rescan: true,
+            ..Default::default()
+        };
+
+        for run in ["first", "second"] {
+            let report = Organiser::new(settings(), Default::default())
+                .organise(vec![library.clone()])
+                .expect("should organise");
+
+            assert!(
+                !report.has_failures(),
+                "{run} run should not organise the journal or index: {:?}",
+                report.files
+            );
+        }
+
+        assert!(
+            library.join("Test").join("Test 001.cbz").exists(),
+            "file should be renamed into a series dir"
+        );
+    }
+
+    #[test]
+    fn test_organise_plan_and_apply() {
+        let dir = TempDir::new().expect("should create temp dir");
+        let source_dir = dir.child("source");
+        let output_dir = dir.child("output");
+
+        let name = "Test 001.cbz";
+        let source_file = source_dir.join(name);
+        let output_file = output_dir.join("Test").join(name);
+
+        std::fs::create_dir_all(&source_dir).expect("should create source dir");
+        fs::write(&source_file, "").expect("should create source file");
+        fs::write(source_dir.join("notes.txt"), "").expect("should create unparseable file");
+
+        let settings = || Settings {
+            output: output_dir.clone(),
+            transfer: Transfer::Move,
+            series: true,
+            journal: true,
+            backup: true,
+            ..Default::default()
+        };
+
+        let organiser = Organiser::new(settings(), Default::default());
+        let (plan, planned) = organiser
+            .plan(vec![source_dir.clone()])
+            .expect("should plan");
+
+        assert!(source_file.exists(), "planning should not move files");
+        assert!(!output_dir.exists(), "planning should not create dirs");
+
+        assert!(
+            plan.steps.iter().any(|step| matches!(
+                step,
+                Step::CreateDir { path, .. } if *path == absolute(&output_dir.join("Test"))
+            )),
+            "plan should create the series dir"
+        );
+        assert!(
+            plan.steps
+                .iter()
+                .any(|step| step.outcome() == Some(Outcome::Moved)),
+            "plan should move the file"
+        );
+        assert!(
+            plan.steps
+                .iter()
+                .any(|step| step.outcome() == Some(Outcome::Unparseable)),
+            "plan should skip the unparseable file"
+        );
+        assert_eq!(planned.counts.moved, 1, "plan report should count the move");
+        assert!(
+            planned.has_failures(),
+            "plan report should count the unparseable file"
+        );
+
+        let organiser = Organiser::new(settings(), Default::default());
+        let report = organiser.apply(plan).expect("should apply plan");
+
+        assert!(!source_file.exists(), "source file should have been moved");
+        assert!(
+            output_file.exists(),
+            "output file should have been created: {}",
+            output_file.display()
+        );
+        assert_eq!(report.counts.moved, 1, "report should count the move");
+        assert_eq!(
+            report.counts.unparseable, 1,
+            "report should count the unparseable file"
+        );
+    }
+
+    #[test]
+    fn test_organise_overrides() {
+        let dir = TempDir::new().expect("should create temp dir");
+        let source_dir = dir.child("source");
+        let sub_dir = source_dir.join("sub");
+        let output_dir = dir.child("output");
+
+        let scan = source_dir.join("scan.txt");
+        let notes = sub_dir.join("notes.txt");
+
+        std::fs::create_dir_all(&sub_dir).expect("should create source dir");
+        fs::write(&scan, "").expect("should create source file");
+        fs::write(&notes, "").expect("should create source file");
+        fs::write(source_dir.join("notes.txt"), "").expect("should create source file");
+
+        let stamp = |path: &Path| Stamp::of(path).expect("should stamp source file");
+
+        let mut overrides = Overrides::load(&output_dir).expect("should load overrides");
+        let meta: Meta = "Test 001 (2020).cbz".parse().expect("should parse meta");
+        overrides.insert(
+            "scan.txt",
+            stamp(&scan),
+            Rule::Use {
+                meta: Box::new(meta),
+            },
+        );
+        overrides.insert("sub/notes.txt", stamp(&notes), Rule::Skip);
+        overrides.save(&output_dir).expect("should save overrides");
+
+        let settings = |use_overrides| Settings {
+            output: output_dir.clone(),
+            recursive: true,
+            use_overrides,
+            remember: true,
+            ..Default::default()
+        };
+
+        let report = Organiser::new(settings(false), Default::default())
+            .organise(vec![source_dir.clone()])
+            .expect("should organise");
+
+        assert_eq!(
+            report.counts.unparseable, 3,
+            "overrides should not be used unless opted in"
+        );
+
+        let report = Organiser::new(settings(true), Default::default())
+            .organise(vec![source_dir.clone()])
+            .expect("should organise");
+
+        let output_file = output_dir.join("Test 001 (2020).cbz");
+        assert!(
+            output_file.exists(),
+            "file should be organised with override metadata: {}",
+            output_file.display()
+        );
+        assert_eq!(report.counts.copied, 1, "one file should be copied");
+        assert_eq!(
+            report.counts.skipped, 1,
+            "file should be skipped by override"
+        );
+        assert_eq!(
+            report.counts.unparseable, 1,
+            "file with the same name in another dir should not use the override"
+        );
+
+        fs::write(&notes, "changed").expect("should change source file");
+
+        let report = Organiser::new(settings(true), Default::default())
+            .organise(vec![source_dir])
+            .expect("should organise");
+
+        assert_eq!(
+            report.counts.unparseable, 2,
+            "changed file should not use the override"
+        );
     }
 }
@@ -0,0 +1,246 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Component, Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::comic::Meta;
+use crate::organise::Stamp;
+use crate::temp;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "kebab-case")]
+/// A remembered answer for organising a file, so it is not asked again.
+pub enum Rule {
+    /// Organise the file as proposed.
+    Accept,
+    /// Leave the file where it is.
+    Skip,
+    /// Organise the file with the provided metadata instead of parsing its name.
+    Use { meta: Box<Meta> },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+/// A rule remembered for a single file.
+struct Remembered {
+    /// Stamp of the file when the rule was remembered.
+    stamp: Stamp,
+    /// The remembered rule.
+    rule: Rule,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+/// Override rules for organising files, kept in the output directory between runs.
+///
+/// Rules are keyed by the path of each file relative to the directory it was organised from,
+/// so files with the same name in different directories get their own rules.
+/// A rule only applies while its file is unchanged.
+pub struct Overrides {
+    /// Version of the overrides format.
+    version: u32,
+    /// Rules by relative path.
+    rules: BTreeMap<String, Remembered>,
+    /// Whether the rules have changed since they were loaded.
+    #[serde(skip)]
+    changed: bool,
+}
+
+impl Overrides {
+    /// Path of the overrides inside the output directory.
+    pub const PATH: &'static str = ".cyborg/overrides.json";
+
+    /// Current version of the overrides format.
+    const VERSION: u32 = 1;
+
+    /// Load the overrides from the provided output directory.
+    ///
+    /// Returns empty overrides if there are none yet.
+    pub fn load(output: &Path) -> io::Result<Overrides> {
+        let path = output.join(Self::PATH);
+
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                log::debug!("no overrides found: {}", path.display());
+                return Ok(Overrides {
+                    version: Self::VERSION,
+                    ..Default::default()
+                });
+            }
+            Err(err) => return Err(err),
+        };
+
+        let overrides: Overrides = serde_json::from_reader(BufReader::new(file))?;
+
+        if overrides.version != Self::VERSION {
+            return Err(io::Error::other(format!(
+                "unsupported overrides version {}: {}",
+                overrides.version,
+                path.display()
+            )));
+        }
+
+        log::debug!("loaded {} override rules", overrides.rules.len());
+
+        Ok(overrides)
+    }
+
+    /// Save the overrides to the provided output directory, if they have changed.
+    ///
+    /// The overrides are written to a temporary file and renamed into place.
+    pub fn save(&mut self, output: &Path) -> io::Result<()> {
+        if !self.changed {
+            return Ok(());
+        }
+
+        let path = output.join(Self::PATH);
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let temp = temp::path(&path);
+
+        let result = File::create(&temp).and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            serde_json::to_writer_pretty(&mut writer, self)?;
+            writer.flush()?;
+            writer.get_ref().sync_data()
+        });
+
+        if let Err(err) = result.and_then(|_| fs::rename(&temp, &path)) {
+            let _ = fs::remove_file(&temp);
+            return Err(err);
+        }
+
+        log::debug!("saved {} override rules", self.rules.len());
+        self.changed = false;
+
+        Ok(())
+    }
+
+    /// Get the key for the file at the provided path, relative to the directory it is organised from.
+    ///
+    /// Components are joined with `/`, so keys are the same on every platform.
+    pub fn key(root: &Path, path: &Path) -> String {
+        let relative = match path.strip_prefix(root) {
+            Ok(relative) if relative.as_os_str().is_empty() => {
+                path.file_name().map(Path::new).unwrap_or(path)
+            }
+            Ok(relative) => relative,
+            Err(_) => path,
+        };
+
+        relative
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(name.to_string_lossy()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// Get the rule for the file with the provided key, if it is unchanged since the rule was remembered.
+    pub fn get(&self, key: &str, stamp: &Stamp) -> Option<&Rule> {
+        let remembered = self.rules.get(key)?;
+
+        if remembered.stamp != *stamp {
+            log::debug!("ignoring override for changed file: {key}");
+            return None;
+        }
+
+        Some(&remembered.rule)
+    }
+
+    /// Remember a rule for the file with the provided key and stamp, replacing any existing rule.
+    pub fn insert(&mut self, key: &str, stamp: Stamp, rule: Rule) {
+        self.rules
+            .insert(key.to_string(), Remembered { stamp, rule });
+        self.changed = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use temp_dir::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_save_and_load() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = TempDir::new()?;
+        let scan = dir.child("scan.cbz");
+        fs::write(&scan, "")?;
+        let stamp = Stamp::of(&scan)?;
+
+        let mut overrides = Overrides::load(dir.path())?;
+        assert_eq!(
+            overrides.get("scan.cbz", &stamp),
+            None,
+            "new overrides should be empty"
+        );
+
+        let meta: Meta = "Test 001 (2020).cbz".parse()?;
+        overrides.insert(
+            "scan.cbz",
+            stamp.clone(),
+            Rule::Use {
+                meta: Box::new(meta.clone()),
+            },
+        );
+        overrides.insert("sub/scan.cbz", stamp.clone(), Rule::Skip);
+        overrides.save(dir.path())?;
+
+        let overrides = Overrides::load(dir.path())?;
+        assert_eq!(
+            overrides.get("scan.cbz", &stamp),
+            Some(&Rule::Use {
+                meta: Box::new(meta)
+            }),
+            "should load saved metadata"
+        );
+        assert_eq!(
+            overrides.get("sub/scan.cbz", &stamp),
+            Some(&Rule::Skip),
+            "should load saved skip"
+        );
+
+        let changed = Stamp {
+            size: stamp.size + 1,
+            ..stamp
+        };
+        assert_eq!(
+            overrides.get("scan.cbz", &changed),
+            None,
+            "changed files should not use their rule"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_key() {
+        assert_eq!(
+            Overrides::key(
+                Path::new("downloads"),
+                Path::new("downloads/Batman/001.cbz")
+            ),
+            "Batman/001.cbz"
+        );
+        assert_eq!(
+            Overrides::key(Path::new("downloads"), Path::new("other/scan.cbz")),
+            "other/scan.cbz",
+            "paths outside the root should be kept"
+        );
+        assert_eq!(
+            Overrides::key(
+                Path::new("downloads/scan.cbz"),
+                Path::new("downloads/scan.cbz")
+            ),
+            "scan.cbz",
+            "a root file should use its name"
+        );
+    }
+}
@@ -0,0 +1,287 @@
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{self, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::comic::{Format, Meta};
+use crate::organise::journal::absolute;
+use crate::organise::{Outcome, Record, Transfer};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "kebab-case")]
+/// A single change to make while organising, with the reason for it.
+pub enum Step {
+    /// Create a directory, along with any missing parents.
+    CreateDir { path: PathBuf, reason: String },
+    /// Transfer a file to the destination.
+    Transfer {
+        source: PathBuf,
+        destination: PathBuf,
+        transfer: Transfer,
+        /// Whether an existing file at the destination is replaced.
+        overwrite: bool,
+        /// Whether the metadata is written to ComicInfo.xml in the organised file.
+        write_info: bool,
+        meta: Meta,
+        reason: String,
+    },
+    /// Convert a file to a CBZ at the destination.
+    Convert {
+        source: PathBuf,
+        destination: PathBuf,
+        /// The format of the source archive.
+        format: Format,
+        /// Whether the source is removed once converted.
+        remove_source: bool,
+        /// Whether an existing file at the destination is replaced.
+        overwrite: bool,
+        /// Whether the metadata is written to ComicInfo.xml in the organised file.
+        write_info: bool,
+        meta: Meta,
+        reason: String,
+    },
+    /// Leave a file where it is.
+    Skip {
+        source: PathBuf,
+        destination: Option<PathBuf>,
+        outcome: Outcome,
+        reason: String,
+    },
+    /// Remove a source directory left empty after moving, along with any junk files in it.
+    RemoveDir {
+        path: PathBuf,
+        junk: Vec<PathBuf>,
+        reason: String,
+    },
+}
+
+impl Step {
+    /// Get the outcome of this step for the file it organises, if it organises a file.
+    pub fn outcome(&self) -> Option<Outcome> {
+        match self {
+            Self::CreateDir { .. } | Self::RemoveDir { .. } => None,
+            Self::Transfer {
+                overwrite: true, ..
+            }
+            | Self::Convert {
+                overwrite: true, ..
+            } => Some(Outcome::Overwritten),
+            Self::Transfer { transfer, .. } => Some(match transfer {
+                Transfer::Copy | Transfer::Reflink => Outcome::Copied,
+                Transfer::Move => Outcome::Moved,
+                Transfer::Hardlink | Transfer::Symlink | Transfer::RelativeSymlink => {
+                    Outcome::Linked
+                }
+            }),
+            Self::Convert { .. } => Some(Outcome::Converted),
+            Self::Skip { outcome, .. } => Some(*outcome),
+        }
+    }
+
+    /// Get the record of the file this step organises, as if it has been applied.
+    pub fn record(&self) -> Option<Record> {
+        let outcome = self.outcome()?;
+
+        let (source, destination, reason) = match self {
+            Self::Transfer {
+                source,
+                destination,
+                ..
+            }
+            | Self::Convert {
+                source,
+                destination,
+                ..
+            } => (source, Some(destination.clone()), None),
+            Self::Skip {
+                source,
+                destination,
+                reason,
+                ..
+            } => (source, destination.clone(), Some(reason.clone())),
+            Self::CreateDir { .. } | Self::RemoveDir { .. } => return None,
+        };
+
+        Some(Record {
+            source: source.clone(),
+            destination,
+            outcome,
+            reason,
+        })
+    }
+
+    /// Make every path in this step absolute, so the plan can be applied from anywhere.
+    fn absolute(mut self) -> Step {
+        match &mut self {
+            Self::CreateDir { path, .. } => *path = absolute(path),
+            Self::Transfer {
+                source,
+                destination,
+                ..
+            }
+            | Self::Convert {
+                source,
+                destination,
+                ..
+            } => {
+                *source = absolute(source);
+                *destination = absolute(destination);
+            }
+            Self::Skip {
+                source,
+                destination,
+                ..
+            } => {
+                *source = absolute(source);
+                *destination = destination.as_deref().map(absolute);
+            }
+            Self::RemoveDir { path, junk, .. } => {
+                *path = absolute(path);
+                *junk = junk.iter().map(|file| absolute(file)).collect();
+            }
+        }
+
+        self
+    }
+}
+
+impl Display for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::CreateDir { path, reason } => {
+                write!(f, "create dir: {} ({reason})", path.display())
+            }
+            Self::Transfer {
+                source,
+                destination,
+                transfer,
+                reason,
+                ..
+            } => write!(
+                f,
+                "{transfer}: {} -> {} ({reason})",
+                source.display(),
+                destination.display()
+            ),
+            Self::Convert {
+                source,
+                destination,
+                reason,
+                ..
+            } => write!(
+                f,
+                "convert: {} -> {} ({reason})",
+                source.display(),
+                destination.display()
+            ),
+            Self::Skip { source, reason, .. } => {
+                write!(f, "skip: {} ({reason})", source.display())
+            }
+            Self::RemoveDir { path, reason, .. } => {
+                write!(f, "remove dir: {} ({reason})", path.display())
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+/// A reviewable plan of the changes organising will make, applied in order.
+pub struct Plan {
+    /// The output directory the plan was made for, where the journal and index are kept.
+    pub output: PathBuf,
+    /// The steps to apply, in order.
+    pub steps: Vec<Step>,
+}
+
+impl Plan {
+    /// Create a new empty plan for the provided output directory.
+    pub fn new(output: &Path) -> Plan {
+        Plan {
+            output: absolute(output),
+            steps: vec![],
+        }
+    }
+
+    /// Add a step to the plan, making its paths absolute.
+    ///
+    /// Returns false without adding the step if it creates a directory already in the plan.
+    pub fn push(&mut self, step: Step) -> bool {
+        let step = step.absolute();
+
+        if let Step::CreateDir { path, .. } = &step {
+            let planned = self.steps.iter().any(|planned| {
+                matches!(planned, Step::CreateDir { path: existing, .. } if existing == path)
+            });
+
+            if planned {
+                return false;
+            }
+        }
+
+        self.steps.push(step);
+        true
+    }
+
+    /// Read a plan from the JSON file at the provided path.
+    pub fn read(path: &Path) -> io::Result<Plan> {
+        let file = File::open(path)?;
+        Ok(serde_json::from_reader(BufReader::new(file))?)
+    }
+
+    /// Write the plan as JSON to the provided writer.
+    pub fn write_json(&self, mut writer: impl Write) -> io::Result<()> {
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writeln!(writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_and_read() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = temp_dir::TempDir::new()?;
+        let mut plan = Plan::new(&dir.child("output"));
+
+        plan.push(Step::CreateDir {
+            path: dir.child("output").join("Test"),
+            reason: "output dir".into(),
+        });
+        plan.push(Step::Transfer {
+            source: PathBuf::from("Test 1.cbz"),
+            destination: dir.child("output").join("Test").join("Test 001.cbz"),
+            transfer: Transfer::Move,
+            overwrite: false,
+            write_info: false,
+            meta: "Test 1.cbz".parse()?,
+            reason: "new file".into(),
+        });
+
+        assert!(
+            plan.steps
+                .iter()
+                .all(|step| !matches!(step, Step::Transfer { source, .. } if source.is_relative())),
+            "paths should be made absolute"
+        );
+        assert_eq!(plan.steps[1].outcome(), Some(Outcome::Moved));
+
+        assert!(
+            !plan.push(Step::CreateDir {
+                path: dir.child("output").join("Test"),
+                reason: "output dir".into(),
+            }),
+            "planned dirs should only be added once"
+        );
+        assert_eq!(plan.steps.len(), 2);
+
+        let path = dir.child("plan.json");
+        plan.write_json(File::create(&path)?)?;
+
+        assert_eq!(Plan::read(&path)?, plan, "plan should round trip");
+
+        Ok(())
+    }
+}
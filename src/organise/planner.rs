@@ -0,0 +1,304 @@
+use std::path::{Path, PathBuf};
+use std::sync::Once;
+
+use anyhow::Context;
+
+use crate::comic::{can_convert, Format, Meta};
+use crate::organise::transfer;
+use crate::organise::{Conflict, Layout, Outcome, Resolution, Settings, Step, Transfer};
+
+/// Planner for the steps that organise each file
+///
+/// Planning only reads the filesystem, so a file can be planned again, such as after
+/// its details are edited. Missing output directories are planned for every file, and
+/// conflicts are resolved again when the steps are applied, as the output may change.
+pub struct Planner<'a> {
+    /// Settings for the organiser
+    settings: &'a Settings,
+}
+
+impl<'a> Planner<'a> {
+    /// Create a new Planner with the provided settings
+    pub fn new(settings: &'a Settings) -> Planner<'a> {
+        Planner { settings }
+    }
+
+    /// Plan how to organise the provided file with its metadata
+    ///
+    /// The detected format decides whether the file is converted,
+    /// and replaces the format of the metadata when fixing extensions.
+    pub fn plan_file(
+        &self,
+        path: &Path,
+        mut comic: Meta,
+        detected: Option<Format>,
+    ) -> anyhow::Result<Vec<Step>> {
+        log::debug!("planning file: {}", path.display());
+
+        if let Some(detected) = detected.filter(|detected| *detected != comic.format) {
+            log::warn!(
+                "extension does not match {} format: {}",
+                detected,
+                path.display()
+            );
+            if self.settings.fix_extension {
+                comic.format = detected;
+            }
+        }
+
+        let source_format = detected.unwrap_or(comic.format);
+        let unsupported =
+            self.settings.convert && source_format != Format::Cbz && !can_convert(source_format);
+
+        if unsupported {
+            static UNSUPPORTED: Once = Once::new();
+            UNSUPPORTED.call_once(|| {
+                log::warn!(
+                    "converting from {source_format} is not supported by this build, \
+                    transferring unconverted"
+                );
+            });
+            log::debug!("transferring unconverted: {}", path.display());
+        }
+
+        let convert = self.settings.convert && source_format != Format::Cbz && !unsupported;
+
+        if self.settings.convert && !unsupported {
+            comic.format = Format::Cbz;
+        }
+
+        let root = self.output_root(path);
+
+        if root.exists() && !root.is_dir() {
+            log::error!("output path is not a directory: {}", root.display());
+            anyhow::bail!("output path is not a directory");
+        }
+
+        let (output_dir, new_path) = self.destination(&root, &comic);
+
+        if new_path.file_name() == path.file_name() && transfer::same_file(path, &new_path) {
+            log::debug!("file is already organised: {}", path.display());
+            return Ok(vec![Step::Skip {
+                source: path.to_path_buf(),
+                destination: Some(new_path),
+                outcome: Outcome::Skipped,
+                reason: "already organised".into(),
+            }]);
+        }
+
+        let mut steps = vec![];
+
+        if !output_dir.exists() {
+            steps.push(Step::CreateDir {
+                path: output_dir.clone(),
+                reason: "output dir does not exist".into(),
+            });
+        }
+
+        let policy = self.settings.conflict;
+
+        let (new_path, overwrite, reason) = match new_path.exists() {
+            false => (new_path, false, "new file".to_string()),
+            true if transfer::is_case_rename(path, &new_path) => {
+                (new_path, false, "case-only rename".to_string())
+            }
+            true => match self.resolve_conflict(path, &new_path)? {
+                Resolution::Overwrite => {
+                    let reason = format!("replacing existing file ({policy})");
+                    (new_path, true, reason)
+                }
+                Resolution::Rename(renamed) => {
+                    if let Some(parent) = renamed.parent().filter(|parent| !parent.exists()) {
+                        steps.push(Step::CreateDir {
+                            path: parent.to_path_buf(),
+                            reason: "conflicts dir does not exist".into(),
+                        });
+                    }
+                    let reason = format!("keeping existing file ({policy})");
+                    (renamed, false, reason)
+                }
+                Resolution::Displace(moved) => {
+                    if let Some(parent) = moved.parent().filter(|parent| !parent.exists()) {
+                        steps.push(Step::CreateDir {
+                            path: parent.to_path_buf(),
+                            reason: "conflicts dir does not exist".into(),
+                        });
+                    }
+                    let reason = format!("moving existing file to {} ({policy})", moved.display());
+                    (new_path, false, reason)
+                }
+                Resolution::Skip => {
+                    steps.push(Step::Skip {
+                        source: path.to_path_buf(),
+                        destination: Some(new_path),
+                        outcome: Outcome::Skipped,
+                        reason: format!("file already exists ({policy})"),
+                    });
+                    return Ok(steps);
+                }
+            },
+        };
+
+        let is_cbz = convert || source_format == Format::Cbz;
+
+        let linked = !convert
+            && matches!(
+                self.settings.transfer,
+                Transfer::Hardlink | Transfer::Symlink | Transfer::RelativeSymlink
+            );
+
+        let write_info = match [self.settings.write_info, is_cbz, linked] {
+            [true, false, _] => {
+                log::debug!(
+                    "skipping comic info for non-cbz file: {}",
+                    new_path.display()
+                );
+                false
+            }
+            [true, true, true] => {
+                static LINKED: Once = Once::new();
+                LINKED.call_once(|| {
+                    log::warn!(
+                        "comic info is not written to linked files, as it would replace the link"
+                    );
+                });
+                log::debug!(
+                    "skipping comic info for linked file: {}",
+                    new_path.display()
+                );
+                false
+            }
+            [write_info, _, _] => write_info,
+        };
+
+        let step = match convert {
+            true => Step::Convert {
+                source: path.to_path_buf(),
+                destination: new_path,
+                format: source_format,
+                remove_source: self.settings.transfer.removes_source(),
+                overwrite,
+                write_info,
+                meta: comic,
+                reason,
+            },
+            false => Step::Transfer {
+                source: path.to_path_buf(),
+                destination: new_path,
+                transfer: self.settings.transfer,
+                overwrite,
+                write_info,
+                meta: comic,
+                reason,
+            },
+        };
+
+        steps.push(step);
+
+        Ok(steps)
+    }
+
+    /// Get the output directory and path to organise a comic into below the provided root
+    pub fn destination(&self, root: &Path, comic: &Meta) -> (PathBuf, PathBuf) {
+        let new_name = format!("{}.{}", self.settings.template.render(comic), comic.format);
+
+        log::trace!("new name: {}", &new_name);
+
+        let subdir = match (&self.settings.layout, self.settings.series) {
+            (Some(layout), _) => Some(layout.render(comic)),
+            (None, true) => Some(Layout::series().render(comic)),
+            (None, false) => None,
+        };
+
+        let output_dir = match subdir {
+            Some(subdir) if self.settings.in_place && root.ends_with(&subdir) => root.to_path_buf(),
+            Some(subdir) => root.join(subdir),
+            None => root.to_path_buf(),
+        };
+
+        log::trace!("output dir: {}", output_dir.display());
+
+        let new_path = output_dir.join(new_name);
+
+        log::trace!("new path: {}", new_path.display());
+
+        (output_dir, new_path)
+    }
+
+    /// Get the directory the provided file is organised into, before any layout
+    ///
+    /// This is the output directory, or the parent of the file when renaming in place.
+    pub fn output_root(&self, path: &Path) -> PathBuf {
+        match self.settings.in_place {
+            true => match path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+                _ => PathBuf::from("."),
+            },
+            false => self.settings.output.clone(),
+        }
+    }
+
+    /// Resolve a conflict with an existing file at the new path using the conflict policy
+    fn resolve_conflict(&self, path: &Path, new_path: &Path) -> anyhow::Result<Resolution> {
+        let policy = self.settings.conflict;
+
+        log::warn!("file already exists ({policy}): {}", new_path.display());
+
+        let conflicts = self.output_root(path).join(Conflict::DIR);
+
+        policy
+            .resolve(path, new_path, &conflicts)
+            .context("resolving conflict")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use temp_dir::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_plan_file() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = TempDir::new()?;
+        let source = dir.child("Test 1.cbz");
+        let output = dir.child("output");
+        fs::write(&source, "")?;
+
+        let settings = Settings {
+            output: output.clone(),
+            series: true,
+            transfer: Transfer::Move,
+            ..Default::default()
+        };
+
+        let planner = Planner::new(&settings);
+        let meta: Meta = "Test 1.cbz".parse()?;
+
+        let steps = planner.plan_file(&source, meta.clone(), Some(Format::Cbz))?;
+
+        assert_eq!(
+            steps.first(),
+            Some(&Step::CreateDir {
+                path: output.join("Test"),
+                reason: "output dir does not exist".into(),
+            }),
+            "missing output dir should be planned first"
+        );
+        assert_eq!(
+            steps.last().and_then(Step::outcome),
+            Some(Outcome::Moved),
+            "file should be planned to move"
+        );
+        assert_eq!(
+            planner.plan_file(&source, meta, Some(Format::Cbz))?,
+            steps,
+            "planning again should plan the same steps"
+        );
+        assert!(!output.exists(), "planning should not change any files");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,309 @@
+use std::ffi::OsString;
+use std::fs::{self, Permissions};
+use std::io;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use filetime::FileTime;
+
+use crate::organise::Stamp;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// Metadata kept from source files, or set on created files and directories.
+///
+/// Copies keep permissions but not times, so attributes are captured from the source
+/// before it is transferred and applied afterwards. Links share the source, so they are left alone.
+pub struct Preserve {
+    /// Keep the modification and access times of source files.
+    pub times: bool,
+    /// Copy extended attributes from source files.
+    pub xattrs: bool,
+    /// User id to own created files and directories.
+    pub owner: Option<u32>,
+    /// Group id to own created files and directories.
+    pub group: Option<u32>,
+    /// Permissions for organised files, instead of those of the source.
+    pub file_mode: Option<u32>,
+    /// Permissions for created directories.
+    pub dir_mode: Option<u32>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Attributes captured from a source file before it is transferred.
+pub struct Attributes {
+    /// Permissions of the source.
+    permissions: Permissions,
+    /// When the source was last accessed.
+    accessed: FileTime,
+    /// When the source was last modified.
+    modified: FileTime,
+    /// Extended attribute names and values, if they are being copied.
+    xattrs: Vec<(OsString, Vec<u8>)>,
+}
+
+impl Preserve {
+    /// Capture the attributes of the source file that should be kept.
+    pub fn capture(&self, source: &Path) -> io::Result<Attributes> {
+        let meta = source.metadata()?;
+
+        let xattrs = match self.xattrs {
+            true => read_xattrs(source)?,
+            false => vec![],
+        };
+
+        Ok(Attributes {
+            permissions: meta.permissions(),
+            accessed: FileTime::from_last_access_time(&meta),
+            modified: FileTime::from_last_modification_time(&meta),
+            xattrs,
+        })
+    }
+
+    /// Apply the captured attributes, ownership and mode to an organised file.
+    ///
+    /// Times are applied last, so nothing else changes them afterwards.
+    pub fn apply_file(&self, attributes: &Attributes, path: &Path) -> io::Result<()> {
+        for (name, value) in &attributes.xattrs {
+            if let Err(err) = write_xattr(path, name, value) {
+                log::warn!(
+                    "failed to copy extended attribute {}: {}: {err}",
+                    name.to_string_lossy(),
+                    path.display()
+                );
+            }
+        }
+
+        match self.file_mode {
+            Some(mode) => set_mode(path, mode)?,
+            None => fs::set_permissions(path, attributes.permissions.clone())?,
+        }
+
+        set_owner(path, self.owner, self.group)?;
+        self.apply_times(attributes, path)
+    }
+
+    /// Apply the captured times to an organised file, if they are being kept.
+    pub fn apply_times(&self, attributes: &Attributes, path: &Path) -> io::Result<()> {
+        if !self.times {
+            return Ok(());
+        }
+
+        log::trace!("keeping times: {}", path.display());
+        filetime::set_file_times(path, attributes.accessed, attributes.modified)
+    }
+
+    /// Get the stamp the organised file will have once the captured attributes are applied.
+    ///
+    /// This lets the file be recorded before its attributes are applied.
+    pub fn stamp(&self, attributes: &Attributes, path: &Path) -> io::Result<Stamp> {
+        let stamp = Stamp::of(path)?;
+
+        if !self.times {
+            return Ok(stamp);
+        }
+
+        let modified = u64::try_from(attributes.modified.unix_seconds())
+            .ok()
+            .map(|secs| {
+                SystemTime::UNIX_EPOCH + Duration::new(secs, attributes.modified.nanoseconds())
+            });
+
+        Ok(Stamp {
+            modified: modified.or(stamp.modified),
+            ..stamp
+        })
+    }
+
+    /// Apply the ownership and mode to a created directory.
+    pub fn apply_dir(&self, path: &Path) -> io::Result<()> {
+        if let Some(mode) = self.dir_mode {
+            set_mode(path, mode)?;
+        }
+
+        set_owner(path, self.owner, self.group)
+    }
+}
+
+/// Parse a user name or id.
+pub fn parse_user(user: &str) -> Result<u32, String> {
+    if let Ok(id) = user.parse() {
+        return Ok(id);
+    }
+
+    #[cfg(unix)]
+    if let Some(found) = uzers::get_user_by_name(user) {
+        return Ok(found.uid());
+    }
+
+    Err(format!("unknown user: {user}"))
+}
+
+/// Parse a group name or id.
+pub fn parse_group(group: &str) -> Result<u32, String> {
+    if let Ok(id) = group.parse() {
+        return Ok(id);
+    }
+
+    #[cfg(unix)]
+    if let Some(found) = uzers::get_group_by_name(group) {
+        return Ok(found.gid());
+    }
+
+    Err(format!("unknown group: {group}"))
+}
+
+/// Parse an octal permission mode, like `664`.
+pub fn parse_mode(mode: &str) -> Result<u32, String> {
+    match u32::from_str_radix(mode, 8) {
+        Ok(mode) if mode <= 0o7777 => Ok(mode),
+        _ => Err(format!("invalid octal mode: {mode}")),
+    }
+}
+
+/// Read all extended attributes of the file at the provided path.
+#[cfg(unix)]
+fn read_xattrs(path: &Path) -> io::Result<Vec<(OsString, Vec<u8>)>> {
+    let names = match xattr::list(path) {
+        Ok(names) => names,
+        Err(err) if err.kind() == io::ErrorKind::Unsupported => {
+            log::debug!("extended attributes are not supported: {}", path.display());
+            return Ok(vec![]);
+        }
+        Err(err) => return Err(err),
+    };
+
+    let mut xattrs = vec![];
+
+    for name in names {
+        if let Some(value) = xattr::get(path, &name)? {
+            xattrs.push((name, value));
+        }
+    }
+
+    Ok(xattrs)
+}
+
+/// Read all extended attributes of the file at the provided path.
+#[cfg(not(unix))]
+fn read_xattrs(path: &Path) -> io::Result<Vec<(OsString, Vec<u8>)>> {
+    log::debug!("extended attributes are not supported: {}", path.display());
+    Ok(vec![])
+}
+
+/// Write an extended attribute to the file at the provided path.
+#[cfg(unix)]
+fn write_xattr(path: &Path, name: &OsString, value: &[u8]) -> io::Result<()> {
+    xattr::set(path, name, value)
+}
+
+/// Write an extended attribute to the file at the provided path.
+#[cfg(not(unix))]
+fn write_xattr(_path: &Path, _name: &OsString, _value: &[u8]) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// Set the permissions of the file or directory at the provided path.
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    log::trace!("setting mode {mode:o}: {}", path.display());
+    fs::set_permissions(path, Permissions::from_mode(mode))
+}
+
+/// Set the permissions of the file or directory at the provided path.
+#[cfg(not(unix))]
+fn set_mode(path: &Path, _mode: u32) -> io::Result<()> {
+    log::warn!("setting mode is not supported: {}", path.display());
+    Ok(())
+}
+
+/// Set the owner and group of the file or directory at the provided path.
+#[cfg(unix)]
+fn set_owner(path: &Path, owner: Option<u32>, group: Option<u32>) -> io::Result<()> {
+    if owner.is_none() && group.is_none() {
+        return Ok(());
+    }
+
+    log::trace!("setting owner {owner:?}:{group:?}: {}", path.display());
+    std::os::unix::fs::chown(path, owner, group)
+}
+
+/// Set the owner and group of the file or directory at the provided path.
+#[cfg(not(unix))]
+fn set_owner(path: &Path, owner: Option<u32>, group: Option<u32>) -> io::Result<()> {
+    if owner.is_some() || group.is_some() {
+        log::warn!("setting ownership is not supported: {}", path.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use temp_dir::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse_user("0"), Ok(0));
+        assert_eq!(parse_group("100"), Ok(100));
+        assert_eq!(parse_mode("664"), Ok(0o664));
+        assert_eq!(parse_mode("2775"), Ok(0o2775));
+        assert!(parse_mode("999").is_err(), "non-octal mode should fail");
+        assert!(parse_mode("17777").is_err(), "oversized mode should fail");
+        assert!(
+            parse_user("no-such-user-cyborg").is_err(),
+            "unknown user should fail"
+        );
+    }
+
+    #[test]
+    fn test_apply_file() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = TempDir::new()?;
+        let source = dir.child("source.cbz");
+        let destination = dir.child("destination.cbz");
+        fs::write(&source, "contents")?;
+
+        let day_ago = SystemTime::now() - Duration::from_secs(24 * 60 * 60);
+        fs::File::options()
+            .write(true)
+            .open(&source)?
+            .set_modified(day_ago)?;
+
+        let preserve = Preserve {
+            times: true,
+            file_mode: Some(0o640),
+            ..Default::default()
+        };
+
+        let attributes = preserve.capture(&source)?;
+        fs::copy(&source, &destination)?;
+        let stamp = preserve.stamp(&attributes, &destination)?;
+        preserve.apply_file(&attributes, &destination)?;
+
+        assert_eq!(
+            Stamp::of(&destination)?,
+            stamp,
+            "stamp should match the file once attributes are applied"
+        );
+
+        assert_eq!(
+            destination.metadata()?.modified()?,
+            day_ago,
+            "modification time should be kept"
+        );
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(
+                destination.metadata()?.permissions().mode() & 0o7777,
+                0o640,
+                "mode should be set"
+            );
+        }
+
+        Ok(())
+    }
+}
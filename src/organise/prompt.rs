@@ -0,0 +1,183 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use dialoguer::{Input, Select};
+
+use crate::comic::{Format, Meta, Number};
+use crate::organise::Rule;
+
+/// Ask whether to organise a file as proposed, or to edit its details or skip it.
+///
+/// Returns None if the question was dismissed, skipping the file without remembering it.
+pub fn review(name: &str, proposed: &Path, meta: &Meta) -> dialoguer::Result<Option<Rule>> {
+    let prompt = format!("organise {name} -> {}?", proposed.display());
+    let choice = Select::new()
+        .with_prompt(prompt)
+        .items(&["accept", "edit details", "skip"])
+        .default(0)
+        .interact_opt()?;
+
+    match choice {
+        Some(0) => Ok(Some(Rule::Accept)),
+        Some(1) => Ok(Some(Rule::Use {
+            meta: Box::new(details(meta.format, Some(meta))?),
+        })),
+        Some(_) => Ok(Some(Rule::Skip)),
+        None => Ok(None),
+    }
+}
+
+/// Ask how to organise a file whose name could not be parsed
+///
+/// Each suggestion is shown with the name it would be organised as, followed by
+/// options to enter the details by hand or to skip the file.
+/// Returns None if the question was dismissed, skipping the file without remembering it.
+pub fn resolve(
+    name: &str,
+    error: &str,
+    format: Format,
+    suggestions: Vec<(String, Meta)>,
+) -> dialoguer::Result<Option<Rule>> {
+    let prompt = format!("could not parse {name} ({error}), organise as?");
+    let mut items: Vec<&str> = suggestions
+        .iter()
+        .map(|(label, _)| label.as_str())
+        .collect();
+    items.extend(["enter details", "skip"]);
+
+    let choice = Select::new()
+        .with_prompt(prompt)
+        .items(&items)
+        .default(0)
+        .interact_opt()?;
+
+    let Some(choice) = choice else {
+        return Ok(None);
+    };
+
+    let count = suggestions.len();
+
+    match choice {
+        _ if choice < count => {
+            let (_, meta) = suggestions
+                .into_iter()
+                .nth(choice)
+                .expect("choice is in range");
+            Ok(Some(Rule::Use {
+                meta: Box::new(meta),
+            }))
+        }
+        _ if choice == count => Ok(Some(Rule::Use {
+            meta: Box::new(details(format, None)?),
+        })),
+        _ => Ok(Some(Rule::Skip)),
+    }
+}
+
+/// Ask for the series, number and year of a comic, starting from any known details
+fn details(format: Format, initial: Option<&Meta>) -> dialoguer::Result<Meta> {
+    let mut series = Input::<String>::new().with_prompt("series");
+    if let Some(meta) = initial {
+        series = series.with_initial_text(&meta.series);
+    }
+    let series = series.interact_text()?;
+
+    let mut number = Input::<String>::new()
+        .with_prompt("number")
+        .allow_empty(true)
+        .validate_with(|input: &String| match input.is_empty() {
+            true => Ok(()),
+            false => input.parse::<Number>().map(|_| ()),
+        });
+    if let Some(initial) = initial.and_then(|meta| meta.number.as_ref()) {
+        number = number.with_initial_text(initial.to_string());
+    }
+    let number = number.interact_text()?;
+
+    let mut year = Input::<String>::new()
+        .with_prompt("year")
+        .allow_empty(true)
+        .validate_with(|input: &String| match input.is_empty() {
+            true => Ok(()),
+            false => input.parse::<usize>().map(|_| ()),
+        });
+    if let Some(initial) = initial.and_then(|meta| meta.year) {
+        year = year.with_initial_text(initial.to_string());
+    }
+    let year = year.interact_text()?;
+
+    let base = Meta {
+        series: String::new(),
+        volume: None,
+        number: None,
+        suffix: None,
+        of: None,
+        title: None,
+        year: None,
+        publisher: None,
+        tags: vec![],
+        format,
+    };
+
+    Ok(Meta {
+        series: series.trim().to_string(),
+        number: number.parse().ok(),
+        year: year.parse().ok(),
+        ..initial.cloned().unwrap_or(base)
+    })
+}
+
+/// Suggest metadata for a file whose name could not be parsed
+///
+/// Suggestions come from the name prefixed with its parent directory, for files kept
+/// in a series directory, then from the name with underscores and dots as spaces.
+pub fn suggestions(path: &Path) -> Vec<Meta> {
+    let (Some(stem), Some(extension)) = (path.file_stem(), path.extension()) else {
+        return vec![];
+    };
+
+    let stem = stem.to_string_lossy();
+    let extension = extension.to_string_lossy();
+    let cleaned = stem.replace(['_', '.'], " ");
+
+    let mut names = vec![];
+
+    if let Some(parent) = path.parent().and_then(Path::file_name) {
+        let parent = parent.to_string_lossy().replace('_', " ");
+        names.push(format!("{parent} {stem}.{extension}"));
+        names.push(format!("{parent} {cleaned}.{extension}"));
+    }
+
+    names.push(format!("{cleaned}.{extension}"));
+
+    let mut seen = HashSet::new();
+
+    names
+        .into_iter()
+        .filter_map(|name| name.parse::<Meta>().ok())
+        .filter(|meta| seen.insert(meta.to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suggestions() {
+        let suggestions = suggestions(Path::new("Batman/001 (2020).cbz"));
+
+        assert_eq!(
+            suggestions.first().map(|meta| meta.to_string()).as_deref(),
+            Some("Batman 001 (2020).cbz"),
+            "should suggest the parent directory as the series first"
+        );
+
+        let suggestions = super::suggestions(Path::new("Saga_002_(2012).cbz"));
+
+        assert!(
+            suggestions.iter().any(|meta| meta.series == "Saga"),
+            "should suggest the name with underscores as spaces"
+        );
+    }
+}
@@ -0,0 +1,174 @@
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+/// What happened to a single file.
+pub enum Outcome {
+    /// The file was copied, or reflinked, to the destination.
+    Copied,
+    /// The file was moved to the destination.
+    Moved,
+    /// The file was hard linked or symlinked at the destination.
+    Linked,
+    /// The file was converted to a CBZ at the destination.
+    Converted,
+    /// The file replaced an existing file at the destination.
+    Overwritten,
+    /// The file was skipped, because of a conflict or a filter.
+    Skipped,
+    /// The file name could not be parsed.
+    Unparseable,
+    /// The file could not be organised.
+    Failed,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+/// The outcome of organising a single file.
+pub struct Record {
+    /// The file that was organised.
+    pub source: PathBuf,
+    /// Where the file was organised to, if it got that far.
+    pub destination: Option<PathBuf>,
+    /// What happened to the file.
+    pub outcome: Outcome,
+    /// Why the file was skipped or failed.
+    pub reason: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+/// Number of files with each outcome.
+pub struct Counts {
+    pub copied: usize,
+    pub moved: usize,
+    pub linked: usize,
+    pub converted: usize,
+    pub overwritten: usize,
+    pub skipped: usize,
+    pub unparseable: usize,
+    pub failed: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+/// Summary of an organising run, with the outcome of every file.
+pub struct Report {
+    /// Whether the run was a dry run, so no changes were made.
+    pub dry_run: bool,
+    /// Whether the run was stopped before every file was organised.
+    pub interrupted: bool,
+    /// Number of files with each outcome.
+    pub counts: Counts,
+    /// The outcome of each file, in the order they were organised.
+    pub files: Vec<Record>,
+}
+
+impl Report {
+    /// Create a new empty report.
+    pub fn new(dry_run: bool) -> Report {
+        Report {
+            dry_run,
+            ..Default::default()
+        }
+    }
+
+    /// Add the outcome of a file to the report.
+    pub fn add(&mut self, record: Record) {
+        let count = match record.outcome {
+            Outcome::Copied => &mut self.counts.copied,
+            Outcome::Moved => &mut self.counts.moved,
+            Outcome::Linked => &mut self.counts.linked,
+            Outcome::Converted => &mut self.counts.converted,
+            Outcome::Overwritten => &mut self.counts.overwritten,
+            Outcome::Skipped => &mut self.counts.skipped,
+            Outcome::Unparseable => &mut self.counts.unparseable,
+            Outcome::Failed => &mut self.counts.failed,
+        };
+
+        *count += 1;
+        self.files.push(record);
+    }
+
+    /// Add the outcomes of another report to this one.
+    pub fn extend(&mut self, other: Report) {
+        self.interrupted |= other.interrupted;
+
+        for record in other.files {
+            self.add(record);
+        }
+    }
+
+    /// Whether any file failed or could not be parsed.
+    pub fn has_failures(&self) -> bool {
+        self.counts.failed > 0 || self.counts.unparseable > 0
+    }
+
+    /// Write the report as JSON to the provided path.
+    pub fn write_json(&self, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writeln!(writer)?;
+        writer.flush()
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Counts {
+            copied,
+            moved,
+            linked,
+            converted,
+            overwritten,
+            skipped,
+            unparseable,
+            failed,
+        } = self.counts;
+
+        if self.dry_run {
+            write!(f, "dry run: ")?;
+        }
+
+        if self.interrupted {
+            write!(f, "interrupted: ")?;
+        }
+
+        write!(
+            f,
+            "{copied} copied, {moved} moved, {linked} linked, {converted} converted, \
+            {overwritten} overwritten, {skipped} skipped, {unparseable} unparseable, {failed} failed"
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add() {
+        let mut report = Report::new(false);
+
+        let outcomes = [Outcome::Copied, Outcome::Copied, Outcome::Skipped];
+
+        for outcome in outcomes {
+            report.add(Record {
+                source: PathBuf::from("source.cbz"),
+                destination: None,
+                outcome,
+                reason: None,
+            });
+        }
+
+        assert_eq!(report.counts.copied, 2);
+        assert_eq!(report.counts.skipped, 1);
+        assert!(!report.has_failures(), "should not have failures");
+        assert_eq!(
+            report.to_string(),
+            "2 copied, 0 moved, 0 linked, 0 converted, 0 overwritten, 1 skipped, 0 unparseable, 0 failed"
+        );
+    }
+}
@@ -1,24 +1,74 @@
 use std::path::PathBuf;
+use std::time::Duration;
 
+use globset::{Glob, GlobMatcher};
+
+use crate::comic::{Prefer, Template};
 use crate::command::Args;
+use crate::organise::{Conflict, Filter, Layout, Preserve, Transfer};
 
 #[derive(Default)]
 /// Settings for the organiser
 pub struct Settings {
-    /// The output directory for the organised files
+    /// The output directory for the organised files, or for the journal, index
+    /// and overrides when renaming in place
     pub output: PathBuf,
-    /// Whether to output files in series subdirectories
+    /// Whether to output files in series and volume subdirectories
     pub series: bool,
-    /// Whether to move files instead of copying them
-    pub move_files: bool,
+    /// The directory layout for organised files, overriding series
+    pub layout: Option<Layout>,
+    /// The template used to name organised files
+    pub template: Template,
+    /// Whether to read metadata from ComicInfo.xml in archives
+    pub read_info: bool,
+    /// Which metadata source wins when they disagree
+    pub prefer: Prefer,
+    /// Whether to write metadata to ComicInfo.xml in organised archives
+    pub write_info: bool,
+    /// Whether to use the detected format for the extension
+    pub fix_extension: bool,
+    /// Whether to convert archives to cbz
+    pub convert: bool,
+    /// Whether to rename files where they are, instead of using the output directory
+    pub in_place: bool,
+    /// How files are transferred to the output directory
+    pub transfer: Transfer,
+    /// Metadata kept from source files or set on created files and directories
+    pub preserve: Preserve,
     /// Whether to perform a dry run
     pub dry_run: bool,
     /// Whether to exit after organising
     pub exit: bool,
-    /// Whether to force overwrite of existing files
-    pub force: bool,
+    /// What to do when an organised file already exists
+    pub conflict: Conflict,
     /// Whether to organise files recursively
     pub recursive: bool,
+    /// How many levels of subdirectories to organise when recursive
+    pub max_depth: Option<usize>,
+    /// Include and exclude patterns for directory entries
+    pub filter: Filter,
+    /// Whether to remove source directories left empty after moving
+    pub prune_empty: bool,
+    /// Names of files that do not stop a directory being pruned
+    pub junk: Vec<GlobMatcher>,
+    /// Whether to record changes in a journal so they can be undone
+    pub journal: bool,
+    /// Whether to keep backups of overwritten, modified or removed files in the journal
+    pub backup: bool,
+    /// Whether to keep an index of organised files in the output directory
+    pub index: bool,
+    /// Whether to organise files again even if they are unchanged since the last run
+    pub rescan: bool,
+    /// Whether to confirm each rename and ask how to organise unparseable files
+    pub interactive: bool,
+    /// Whether to use remembered answers without asking
+    pub use_overrides: bool,
+    /// Whether answers are remembered in the output directory between runs
+    pub remember: bool,
+    /// Whether to keep watching the paths for new files
+    pub watch: bool,
+    /// How long a watched file must stop changing before it is organised
+    pub settle: Duration,
 }
 
 impl Settings {
@@ -28,15 +78,59 @@ impl Settings {
     }
 
     /// Create a new OrganiserSettings instance from the provided Args
+    ///
+    /// Renaming in place without an output directory keeps no journal, index or overrides,
+    /// rather than writing them to the current directory.
     pub fn from_args(args: &Args) -> Settings {
+        let stateless = args.in_place && args.output.is_none();
+
+        if stateless {
+            log::warn!("no output directory given, so no journal, index or overrides will be kept");
+        }
+
         Settings {
-            output: args.output.clone(),
+            output: args.output.clone().unwrap_or_else(|| PathBuf::from(".")),
             series: args.series,
-            move_files: args.move_files,
+            layout: args.layout.clone(),
+            template: args.template.clone(),
+            read_info: args.read_info,
+            prefer: args.prefer,
+            write_info: args.write_info,
+            fix_extension: args.fix_extension,
+            convert: args.convert,
+            in_place: args.in_place,
+            transfer: match args.in_place {
+                true => Transfer::Move,
+                false => args.transfer,
+            },
+            preserve: Preserve {
+                times: args.preserve_times,
+                xattrs: args.preserve_xattrs,
+                owner: args.owner,
+                group: args.group,
+                file_mode: args.file_mode,
+                dir_mode: args.dir_mode,
+            },
             dry_run: args.dry_run,
             exit: args.exit,
-            force: args.force,
+            conflict: match args.force {
+                true => Conflict::Overwrite,
+                false => args.conflict,
+            },
             recursive: args.recursive,
+            max_depth: args.max_depth,
+            filter: Filter::new(&args.include, &args.exclude),
+            prune_empty: args.prune_empty,
+            junk: args.junk.iter().map(Glob::compile_matcher).collect(),
+            journal: !args.no_journal && !stateless,
+            backup: !args.no_backup,
+            index: !args.no_index && !stateless,
+            rescan: args.rescan,
+            interactive: args.interactive,
+            use_overrides: args.use_overrides,
+            remember: !stateless,
+            watch: args.watch,
+            settle: Duration::from_secs(args.settle),
         }
     }
 }
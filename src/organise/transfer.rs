@@ -0,0 +1,457 @@
+use std::fmt::Display;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use filetime::FileTime;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::temp;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+/// How organised files are transferred to the output directory.
+pub enum Transfer {
+    /// Copy files, keeping the source.
+    #[default]
+    Copy,
+    /// Move files, removing the source.
+    ///
+    /// Files moved across devices are copied and verified before the source is removed.
+    Move,
+    /// Hard link files to the source.
+    Hardlink,
+    /// Symlink files to the absolute path of the source.
+    Symlink,
+    /// Symlink files to the source, relative to the output file.
+    RelativeSymlink,
+    /// Copy files with copy-on-write reflinks, on filesystems that support them.
+    Reflink,
+}
+
+impl Transfer {
+    /// Whether the source is removed by this transfer.
+    pub fn removes_source(self) -> bool {
+        self == Self::Move
+    }
+
+    /// Whether this transfer atomically replaces an existing file at the destination.
+    pub fn replaces_existing(self) -> bool {
+        matches!(self, Self::Copy | Self::Move)
+    }
+
+    /// Whether this transfer creates a symlink to the source.
+    pub fn is_symlink(self) -> bool {
+        matches!(self, Self::Symlink | Self::RelativeSymlink)
+    }
+
+    /// Transfer the file at the source path to the destination path.
+    ///
+    /// Hard links and reflinks fall back to copying when they are not supported,
+    /// such as across devices. Any other error, such as an existing destination,
+    /// is returned. Returns the transfer that was actually used.
+    pub fn apply(self, source: &Path, destination: &Path) -> io::Result<Transfer> {
+        let result = match self {
+            Self::Copy => copy_file(source, destination, false),
+            Self::Move => move_file(source, destination),
+            Self::Hardlink => fs::hard_link(source, destination),
+            Self::Symlink => symlink(&std::path::absolute(source)?, destination),
+            Self::RelativeSymlink => {
+                let target = relative_target(source, destination)?;
+                symlink(&target, destination)
+            }
+            Self::Reflink => reflink_copy::reflink(source, destination),
+        };
+
+        match (self, result) {
+            (_, Ok(())) => Ok(self),
+            (Self::Hardlink | Self::Reflink, Err(err)) if is_unsupported(&err) => {
+                log::warn!(
+                    "{self} is not supported, falling back to copy: {}: {err}",
+                    destination.display()
+                );
+                if self == Self::Reflink {
+                    // A failed clone can leave an empty destination behind.
+                    let _ = fs::remove_file(destination);
+                }
+                copy_file(source, destination, false)?;
+                Ok(Self::Copy)
+            }
+            (_, Err(err)) => Err(err),
+        }
+    }
+}
+
+impl Display for Transfer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Copy => write!(f, "copy"),
+            Self::Move => write!(f, "move"),
+            Self::Hardlink => write!(f, "hardlink"),
+            Self::Symlink => write!(f, "symlink"),
+            Self::RelativeSymlink => write!(f, "relative symlink"),
+            Self::Reflink => write!(f, "reflink"),
+        }
+    }
+}
+
+/// Whether the error means a link or clone is not supported between the paths.
+fn is_unsupported(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::Unsupported | io::ErrorKind::CrossesDevices
+    )
+}
+
+/// Move a file, renaming it where possible.
+///
+/// When the destination is on another device the file is copied instead,
+/// and the source is only removed once the copy has been verified.
+pub(crate) fn move_file(source: &Path, destination: &Path) -> io::Result<()> {
+    if is_case_rename(source, destination) {
+        return case_rename(source, destination);
+    }
+
+    match fs::rename(source, destination) {
+        Err(err) if err.kind() == io::ErrorKind::CrossesDevices => {
+            log::debug!(
+                "moving across devices: {} -> {}",
+                source.display(),
+                destination.display()
+            );
+            move_copied(source, destination)
+        }
+        result => result,
+    }
+}
+
+/// Move a file by copying it, keeping its times as a rename would.
+///
+/// The source is only removed once the copy has been verified.
+fn move_copied(source: &Path, destination: &Path) -> io::Result<()> {
+    let meta = source.metadata()?;
+    copy_file(source, destination, true)?;
+    filetime::set_file_times(
+        destination,
+        FileTime::from_last_access_time(&meta),
+        FileTime::from_last_modification_time(&meta),
+    )?;
+    fs::remove_file(source)
+}
+
+/// Rename a file to a name that only differs by case, through a hidden temporary file.
+///
+/// Some case-insensitive filesystems ignore a direct rename that only changes case.
+fn case_rename(source: &Path, destination: &Path) -> io::Result<()> {
+    log::debug!(
+        "renaming case only: {} -> {}",
+        source.display(),
+        destination.display()
+    );
+
+    let temp = temp::path(destination);
+    fs::rename(source, &temp)?;
+
+    fs::rename(&temp, destination).inspect_err(|_| {
+        let _ = fs::rename(&temp, source);
+    })
+}
+
+/// Whether the destination is the source with a file name that only differs by case,
+/// as it is on case-insensitive filesystems.
+pub(crate) fn is_case_rename(source: &Path, destination: &Path) -> bool {
+    let (Some(from), Some(to)) = (source.file_name(), destination.file_name()) else {
+        return false;
+    };
+
+    from != to
+        && from.to_string_lossy().to_lowercase() == to.to_string_lossy().to_lowercase()
+        && same_file(source, destination)
+}
+
+/// Whether both paths refer to the same file.
+#[cfg(unix)]
+pub(crate) fn same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match (a.metadata(), b.metadata()) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+/// Whether both paths refer to the same file.
+#[cfg(not(unix))]
+pub(crate) fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Copy a file through a hidden temporary file next to the destination.
+///
+/// The copy is synced to disk before it is renamed into place, so an interrupted
+/// copy never leaves a partial file at the destination. When verifying, the copy
+/// must also match the source by size and checksum.
+pub(crate) fn copy_file(source: &Path, destination: &Path, verify: bool) -> io::Result<()> {
+    let temp = temp::path(destination);
+
+    let result = copy_synced(source, &temp, verify).and_then(|_| fs::rename(&temp, destination));
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+
+    result
+}
+
+/// Copy a file and sync it to disk, optionally checking it matches the source.
+fn copy_synced(source: &Path, destination: &Path, verify: bool) -> io::Result<()> {
+    fs::copy(source, destination)?;
+    OpenOptions::new()
+        .write(true)
+        .open(destination)?
+        .sync_all()?;
+
+    if !verify {
+        return Ok(());
+    }
+
+    let matches = source.metadata()?.len() == destination.metadata()?.len()
+        && checksum(source)? == checksum(destination)?;
+
+    if !matches {
+        return Err(io::Error::other(format!(
+            "copy does not match source: {}",
+            destination.display()
+        )));
+    }
+
+    log::trace!("verified copy: {}", destination.display());
+
+    Ok(())
+}
+
+/// Get the SHA-256 checksum of the file at the provided path.
+pub(crate) fn checksum(path: &Path) -> io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 64 * 1024];
+
+    loop {
+        match file.read(&mut buffer)? {
+            0 => break,
+            read => hasher.update(&buffer[..read]),
+        }
+    }
+
+    Ok(hasher.finalize().to_vec())
+}
+
+/// Get the target for a symlink at the destination pointing to the source.
+fn relative_target(source: &Path, destination: &Path) -> io::Result<PathBuf> {
+    let source = fs::canonicalize(source)?;
+    let dir = match destination.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => fs::canonicalize(parent)?,
+        _ => std::env::current_dir()?,
+    };
+
+    Ok(relative_path(&dir, &source))
+}
+
+/// Get the path to `to` relative to the directory `from`.
+///
+/// Both paths must be absolute.
+fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    let common = from
+        .components()
+        .zip(to.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut path = PathBuf::new();
+
+    for _ in from.components().skip(common) {
+        path.push("..");
+    }
+
+    for component in to.components().skip(common) {
+        path.push(component);
+    }
+
+    path
+}
+
+/// Create a symlink to a file.
+#[cfg(unix)]
+fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+/// Create a symlink to a file.
+#[cfg(windows)]
+fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(target, link)
+}
+
+#[cfg(test)]
+mod tests {
+    use temp_dir::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_relative_path() {
+        let cases = [
+            ("/a/b", "/a/b/c.cbz", "c.cbz"),
+            ("/a/b", "/a/c/d.cbz", "../c/d.cbz"),
+            ("/a/b/c", "/d.cbz", "../../../d.cbz"),
+        ];
+
+        for (from, to, expected) in cases {
+            assert_eq!(
+                relative_path(Path::new(from), Path::new(to)),
+                PathBuf::from(expected)
+            );
+        }
+    }
+
+    #[test]
+    fn test_copy_file() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = TempDir::new()?;
+        let source = dir.child("source.cbz");
+        let destination = dir.child("destination.cbz");
+        fs::write(&source, "contents")?;
+
+        copy_file(&source, &destination, true)?;
+
+        assert_eq!(fs::read_to_string(&destination)?, "contents");
+        assert!(
+            !temp::path(&destination).exists(),
+            "temporary file should be renamed into place"
+        );
+        assert_eq!(checksum(&source)?, checksum(&destination)?);
+        assert_ne!(
+            checksum(&source)?,
+            checksum(&dir.child("missing")).unwrap_or_default(),
+            "different files should not match"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_move_copied() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = TempDir::new()?;
+        let source = dir.child("source.cbz");
+        let destination = dir.child("destination.cbz");
+        fs::write(&source, "contents")?;
+
+        let modified = FileTime::from_unix_time(1_000_000_000, 0);
+        filetime::set_file_mtime(&source, modified)?;
+
+        move_copied(&source, &destination)?;
+
+        assert!(!source.exists(), "source should have been removed");
+        assert_eq!(fs::read_to_string(&destination)?, "contents");
+        assert_eq!(
+            FileTime::from_last_modification_time(&destination.metadata()?),
+            modified,
+            "moved file should keep its modified time"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = TempDir::new()?;
+        let source = dir.child("source.cbz");
+        fs::write(&source, "contents")?;
+
+        let output = dir.child("output");
+        fs::create_dir_all(&output)?;
+
+        let modes = [
+            Transfer::Copy,
+            Transfer::Hardlink,
+            Transfer::Symlink,
+            Transfer::RelativeSymlink,
+            Transfer::Reflink,
+        ];
+
+        for mode in modes {
+            let destination = output.join(format!("{mode}.cbz"));
+            let used = mode.apply(&source, &destination)?;
+
+            assert!(
+                used == mode || used == Transfer::Copy,
+                "should use {mode} or fall back to copy"
+            );
+            assert_eq!(fs::read_to_string(&destination)?, "contents");
+            assert_eq!(
+                destination.symlink_metadata()?.is_symlink(),
+                mode.is_symlink(),
+                "only symlink modes should create symlinks"
+            );
+        }
+
+        let link = fs::read_link(output.join("relative symlink.cbz"))?;
+        assert_eq!(link, PathBuf::from("../source.cbz"));
+
+        assert!(source.exists(), "source should be kept");
+
+        let existing = output.join("existing.cbz");
+        fs::write(&existing, "existing")?;
+
+        for mode in [Transfer::Hardlink, Transfer::Reflink] {
+            let result = mode.apply(&source, &existing);
+
+            assert!(
+                result.is_err(),
+                "{mode} should not fall back to copy over an existing file"
+            );
+            assert_eq!(
+                fs::read_to_string(&existing)?,
+                "existing",
+                "existing file should be kept"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_case_rename() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = TempDir::new()?;
+        let source = dir.child("Test 001.CBZ");
+        let destination = dir.child("Test 001.cbz");
+        fs::write(&source, "contents")?;
+
+        // A hard link stands in for the same file on a case-insensitive filesystem.
+        fs::hard_link(&source, &destination)?;
+        assert!(
+            is_case_rename(&source, &destination),
+            "should detect a case-only rename"
+        );
+        assert!(
+            !is_case_rename(&source, &dir.child("Other 001.cbz")),
+            "should not detect a rename to another name"
+        );
+
+        fs::remove_file(&destination)?;
+        case_rename(&source, &destination)?;
+
+        assert!(!source.exists(), "source name should be gone");
+        assert_eq!(fs::read_to_string(&destination)?, "contents");
+        assert!(
+            !temp::path(&destination).exists(),
+            "temporary name should be renamed into place"
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+/// A file that has changed recently and is waiting to settle.
+struct Pending {
+    /// Size of the file when it was last checked.
+    size: u64,
+    /// When the file was last seen to change.
+    changed: Instant,
+}
+
+/// Watches paths for new files, returning them once they have stopped changing.
+///
+/// Events are debounced per file, and a file is only ready once its size
+/// has stayed the same for the settle duration.
+pub struct Watch {
+    /// The filesystem watcher, kept alive while watching.
+    _watcher: RecommendedWatcher,
+    /// Events from the watcher.
+    events: Receiver<notify::Result<Event>>,
+    /// Files waiting to settle.
+    pending: HashMap<PathBuf, Pending>,
+    /// How long a file must stay the same size before it is ready.
+    settle: Duration,
+}
+
+impl Watch {
+    /// Start watching the provided paths.
+    pub fn new(paths: &[PathBuf], recursive: bool, settle: Duration) -> notify::Result<Watch> {
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+
+        let mode = match recursive {
+            true => RecursiveMode::Recursive,
+            false => RecursiveMode::NonRecursive,
+        };
+
+        for path in paths {
+            log::debug!("watching: {}", path.display());
+            watcher.watch(path, mode)?;
+        }
+
+        Ok(Watch {
+            _watcher: watcher,
+            events,
+            pending: HashMap::new(),
+            settle,
+        })
+    }
+
+    /// Wait up to the timeout for events, then return the files that have settled.
+    pub fn ready(&mut self, timeout: Duration) -> Vec<PathBuf> {
+        match self.events.recv_timeout(timeout) {
+            Ok(event) => self.handle(event),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => log::warn!("watcher stopped sending events"),
+        }
+
+        while let Ok(event) = self.events.try_recv() {
+            self.handle(event);
+        }
+
+        let now = Instant::now();
+        let mut ready = vec![];
+
+        self.pending.retain(|path, pending| {
+            if now.duration_since(pending.changed) < self.settle {
+                return true;
+            }
+
+            let size = match path.metadata() {
+                Ok(metadata) if metadata.is_file() => metadata.len(),
+                _ => {
+                    log::trace!("pending file is gone: {}", path.display());
+                    return false;
+                }
+            };
+
+            if size != pending.size {
+                log::trace!("pending file is still growing: {}", path.display());
+                pending.size = size;
+                pending.changed = now;
+                return true;
+            }
+
+            log::debug!("file has settled: {}", path.display());
+            ready.push(path.clone());
+            false
+        });
+
+        ready.sort();
+        ready
+    }
+
+    /// Handle a single watcher event.
+    fn handle(&mut self, event: notify::Result<Event>) {
+        let event = match event {
+            Ok(event) => event,
+            Err(err) => {
+                log::warn!("watch error: {err:#}");
+                return;
+            }
+        };
+
+        if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+            return;
+        }
+
+        for path in event.paths {
+            self.touch(&path);
+        }
+    }
+
+    /// Mark the file at the provided path as changed.
+    fn touch(&mut self, path: &Path) {
+        let size = match path.metadata() {
+            Ok(metadata) if metadata.is_file() => metadata.len(),
+            _ => return,
+        };
+
+        log::trace!("file changed: {}", path.display());
+
+        self.pending.insert(
+            path.to_path_buf(),
+            Pending {
+                size,
+                changed: Instant::now(),
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use temp_dir::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_ready_after_settling() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = TempDir::new()?;
+        let settle = Duration::from_millis(200);
+
+        let mut watch = Watch::new(&[dir.path().to_path_buf()], false, settle)?;
+
+        let file = dir.child("Test 001.cbz");
+        fs::write(&file, "contents")?;
+
+        let ready = watch.ready(Duration::from_millis(50));
+        assert!(ready.is_empty(), "file should not be ready before settling");
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut ready = vec![];
+
+        while ready.is_empty() && Instant::now() < deadline {
+            ready = watch.ready(Duration::from_millis(50));
+        }
+
+        assert_eq!(ready, [file], "file should be ready once settled");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,67 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Suffix added to hidden temporary files.
+pub(crate) const SUFFIX: &str = ".cyborg-tmp";
+
+/// Get the hidden temporary path used while writing the provided file.
+///
+/// The temporary file is placed next to the final file,
+/// so it can be renamed into place on the same filesystem.
+pub(crate) fn path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{name}{SUFFIX}"))
+}
+
+/// Whether the provided path is a hidden temporary file.
+pub(crate) fn is_temp(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with('.') && name.ends_with(SUFFIX))
+}
+
+/// Remove temporary files left in the provided directory by interrupted runs.
+///
+/// Returns the number of files removed.
+pub(crate) fn clean(dir: &Path) -> io::Result<usize> {
+    let mut removed = 0;
+
+    for entry in dir.read_dir()? {
+        let path = entry?.path();
+
+        if is_temp(&path) && path.is_file() {
+            log::info!("removing leftover temporary file: {}", path.display());
+            fs::remove_file(&path)?;
+            removed += 1;
+        }
+    }
+
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use temp_dir::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_clean() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = TempDir::new()?;
+
+        let file = dir.child("Test 001.cbz");
+        let temp = path(&file);
+        fs::write(&file, "")?;
+        fs::write(&temp, "")?;
+
+        assert!(is_temp(&temp), "should be a temporary file");
+        assert!(!is_temp(&file), "should not be a temporary file");
+
+        assert_eq!(clean(dir.path())?, 1);
+        assert!(!temp.exists(), "temporary file should be removed");
+        assert!(file.exists(), "other files should be kept");
+
+        Ok(())
+    }
+}